@@ -0,0 +1,472 @@
+use crate::{
+    parse_decimal, resolve_columns, serialize_to_string, split_fields, AnalyzedLine, Arguments,
+    ColumnType, NumberFormat, TypedValue, UniqueError, UniqueResult, DATA_FORMAT,
+};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::{collections::HashMap, str::FromStr};
+
+/// Sufixo de `--group-by` que agrupa uma coluna de datas por mês (MM/AAAA).
+const MONTH_SUFFIX: &str = ":month";
+
+/// Coluna de agrupamento (`--group-by`).
+#[derive(Debug, Clone, Copy)]
+struct GroupColumn {
+    index: usize,
+    by_month: bool,
+}
+
+/// Valor comparado por `--min` e `--max`.
+///
+/// Valores de tipos diferentes na mesma coluna são ordenados por tipo:
+/// datas, números e, por último, textos.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Extreme {
+    Date(NaiveDate),
+    Number(Decimal),
+    Text(String),
+}
+
+/// Valor de uma célula: texto da saída CSV e valor tipado.
+type Cell = (String, TypedValue);
+
+/// Totais de um grupo.
+#[derive(Debug)]
+struct Group {
+    /// Linha da primeira ocorrência do grupo.
+    line_number: usize,
+    keys: Vec<Cell>,
+    count: usize,
+    sums: Vec<Decimal>,
+    mins: Vec<Option<(Extreme, Cell)>>,
+    maxs: Vec<Option<(Extreme, Cell)>>,
+}
+
+/// Agregação das linhas únicas por grupo (`--group-by`, `--sum`, `--count`,
+/// `--min` e `--max`).
+///
+/// As somas usam aritmética decimal exata. Os grupos são gravados na ordem
+/// em que aparecem pela primeira vez.
+#[derive(Debug)]
+pub struct Aggregation {
+    /// Cabeçalho das linhas processadas.
+    names: Vec<String>,
+    group_columns: Vec<GroupColumn>,
+    sum_columns: Vec<usize>,
+    min_columns: Vec<usize>,
+    max_columns: Vec<usize>,
+    count: bool,
+    number_format: NumberFormat,
+    groups: Vec<Group>,
+    /// Chave do grupo (textos das colunas de agrupamento) => posição em `groups`.
+    positions: HashMap<Vec<String>, usize>,
+}
+
+impl Aggregation {
+    /// Resolve as colunas das opções no cabeçalho das linhas processadas.
+    pub fn new(args: &Arguments, header: &[String]) -> UniqueResult<Self> {
+        let group_columns = args
+            .group_by
+            .iter()
+            .map(|spec| match spec.strip_suffix(MONTH_SUFFIX) {
+                Some(column) => Ok(GroupColumn {
+                    index: resolve_columns(&[column.to_string()], header)?[0],
+                    by_month: true,
+                }),
+                None => Ok(GroupColumn {
+                    index: resolve_columns(std::slice::from_ref(spec), header)?[0],
+                    by_month: false,
+                }),
+            })
+            .collect::<UniqueResult<Vec<GroupColumn>>>()?;
+
+        Ok(Self {
+            names: header.to_vec(),
+            group_columns,
+            sum_columns: resolve_columns(&args.sum, header)?,
+            min_columns: resolve_columns(&args.min, header)?,
+            max_columns: resolve_columns(&args.max, header)?,
+            count: args.count,
+            number_format: args.number_format,
+            groups: Vec::new(),
+            positions: HashMap::new(),
+        })
+    }
+
+    /// Cabeçalho das linhas agregadas: colunas de agrupamento, `count`,
+    /// `sum(coluna)`, `min(coluna)` e `max(coluna)`.
+    pub fn header(&self) -> Vec<String> {
+        let mut header: Vec<String> = self
+            .group_columns
+            .iter()
+            .map(|group| match group.by_month {
+                true => format!("month({})", self.names[group.index]),
+                false => self.names[group.index].clone(),
+            })
+            .collect();
+
+        if self.count {
+            header.push("count".to_string());
+        }
+        for (function, columns) in [
+            ("sum", &self.sum_columns),
+            ("min", &self.min_columns),
+            ("max", &self.max_columns),
+        ] {
+            header.extend(
+                columns
+                    .iter()
+                    .map(|&index| format!("{function}({})", self.names[index])),
+            );
+        }
+
+        header
+    }
+
+    /// Tipos das colunas agregadas, a partir dos tipos das linhas processadas.
+    pub fn column_types(&self, types: &[ColumnType]) -> Vec<ColumnType> {
+        let type_of = |index: usize| types.get(index).copied().unwrap_or(ColumnType::String);
+
+        let mut column_types: Vec<ColumnType> = self
+            .group_columns
+            .iter()
+            .map(|group| match group.by_month {
+                true => ColumnType::String,
+                false => type_of(group.index),
+            })
+            .collect();
+
+        if self.count {
+            column_types.push(ColumnType::Integer);
+        }
+        column_types.extend(self.sum_columns.iter().map(|_| ColumnType::Decimal));
+        column_types.extend(self.min_columns.iter().map(|&index| type_of(index)));
+        column_types.extend(self.max_columns.iter().map(|&index| type_of(index)));
+
+        column_types
+    }
+
+    /// Acrescenta uma linha processada ao seu grupo.
+    ///
+    /// Linhas vazias e linhas mantidas sem processamento (`--on-error keep-raw`)
+    /// são ignoradas; valores nulos não entram nas somas nem nos extremos.
+    pub fn add(&mut self, analyzed: &AnalyzedLine, args: &Arguments) -> UniqueResult<()> {
+        let Some(values) = analyzed.values.as_ref().filter(|_| !analyzed.is_empty) else {
+            return Ok(());
+        };
+        let fields = split_fields(&analyzed.content, args)?;
+        let cell = |index: usize| -> Cell {
+            (
+                fields.get(index).cloned().unwrap_or_default(),
+                values.get(index).cloned().unwrap_or_default(),
+            )
+        };
+
+        let keys: Vec<Cell> = self
+            .group_columns
+            .iter()
+            .map(|group| match group.by_month {
+                true => month(cell(group.index)),
+                false => cell(group.index),
+            })
+            .collect();
+        let key: Vec<String> = keys.iter().map(|(text, _)| text.clone()).collect();
+
+        let position = match self.positions.get(&key) {
+            Some(&position) => position,
+            None => {
+                self.groups.push(Group {
+                    line_number: analyzed.line_number,
+                    keys,
+                    count: 0,
+                    sums: vec![Decimal::ZERO; self.sum_columns.len()],
+                    mins: vec![None; self.min_columns.len()],
+                    maxs: vec![None; self.max_columns.len()],
+                });
+                self.positions.insert(key, self.groups.len() - 1);
+                self.groups.len() - 1
+            }
+        };
+        let group = &mut self.groups[position];
+
+        group.count += 1;
+
+        for (sum, &index) in group.sums.iter_mut().zip(&self.sum_columns) {
+            let (text, value) = cell(index);
+            let number = match &value {
+                TypedValue::Null => continue,
+                TypedValue::Text(text) if text.trim().is_empty() => continue,
+                TypedValue::Integer(n) => Some(Decimal::from(*n)),
+                TypedValue::Float(n) => float_to_decimal(&text, *n, self.number_format),
+                TypedValue::Decimal(n) => Some(*n),
+                TypedValue::Text(text) => parse_decimal(text, self.number_format),
+                TypedValue::Date(_) => None,
+            };
+            *sum += number.ok_or_else(|| UniqueError::Aggregation {
+                line_number: analyzed.line_number,
+                column: self.names[index].clone(),
+                value: text,
+            })?;
+        }
+
+        for (min, &index) in group.mins.iter_mut().zip(&self.min_columns) {
+            let cell = cell(index);
+            if let Some(extreme) = extreme(&cell, self.number_format) {
+                if min.as_ref().is_none_or(|(current, _)| extreme < *current) {
+                    *min = Some((extreme, cell));
+                }
+            }
+        }
+
+        for (max, &index) in group.maxs.iter_mut().zip(&self.max_columns) {
+            let cell = cell(index);
+            if let Some(extreme) = extreme(&cell, self.number_format) {
+                if max.as_ref().is_none_or(|(current, _)| extreme > *current) {
+                    *max = Some((extreme, cell));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Linhas agregadas, uma por grupo, no formato das linhas processadas.
+    ///
+    /// As somas mantêm as casas decimais dos valores somados (ex.: "1253.50").
+    pub fn lines(&self, args: &Arguments) -> UniqueResult<Vec<AnalyzedLine>> {
+        self.groups
+            .iter()
+            .map(|group| {
+                let mut cells: Vec<Cell> = group.keys.clone();
+
+                if self.count {
+                    cells.push((
+                        group.count.to_string(),
+                        TypedValue::Integer(group.count as i64),
+                    ));
+                }
+                cells.extend(
+                    group
+                        .sums
+                        .iter()
+                        .map(|sum| (sum.to_string(), TypedValue::Decimal(*sum))),
+                );
+                for extremes in [&group.mins, &group.maxs] {
+                    cells.extend(
+                        extremes.iter().map(|extreme| {
+                            extreme.clone().map(|(_, cell)| cell).unwrap_or_default()
+                        }),
+                    );
+                }
+
+                let (fields, values): (Vec<String>, Vec<TypedValue>) = cells.into_iter().unzip();
+
+                Ok(AnalyzedLine {
+                    content: serialize_to_string(&fields, args)?,
+                    column_count: fields.len(),
+                    is_empty: false,
+                    values: Some(values),
+                    ..AnalyzedLine::empty(group.line_number)
+                })
+            })
+            .collect()
+    }
+}
+
+/// Mês (MM/AAAA) de uma data; outros valores ficam inalterados.
+fn month((text, value): Cell) -> Cell {
+    let date = match &value {
+        TypedValue::Date(date) => Some(*date),
+        _ => NaiveDate::parse_from_str(text.trim(), DATA_FORMAT).ok(),
+    };
+
+    match date {
+        Some(date) => {
+            let month = date.format("%m/%Y").to_string();
+            (month.clone(), TypedValue::Text(month))
+        }
+        None => (text, value),
+    }
+}
+
+/// Valor comparável de uma célula (`None` se nula).
+///
+/// Textos são lidos como datas (DD/MM/AAAA) ou números, quando possível.
+fn extreme((text, value): &Cell, number_format: NumberFormat) -> Option<Extreme> {
+    match value {
+        TypedValue::Null => None,
+        TypedValue::Date(date) => Some(Extreme::Date(*date)),
+        TypedValue::Integer(n) => Some(Extreme::Number(Decimal::from(*n))),
+        TypedValue::Decimal(n) => Some(Extreme::Number(*n)),
+        TypedValue::Float(n) => Some(match float_to_decimal(text, *n, number_format) {
+            Some(number) => Extreme::Number(number),
+            None => Extreme::Text(n.to_string()),
+        }),
+        TypedValue::Text(text) if text.trim().is_empty() => None,
+        TypedValue::Text(text) => Some(
+            if let Ok(date) = NaiveDate::parse_from_str(text.trim(), DATA_FORMAT) {
+                Extreme::Date(date)
+            } else if let Some(number) = parse_decimal(text, number_format) {
+                Extreme::Number(number)
+            } else {
+                Extreme::Text(text.clone())
+            },
+        ),
+    }
+}
+
+/// Valor decimal exato de um número lido como f64, a partir do texto da célula
+/// (formato computacional de `-f`, `--format` e `-m`), sem passar pelo f64.
+fn float_to_decimal(text: &str, number: f64, number_format: NumberFormat) -> Option<Decimal> {
+    Decimal::from_str(text.trim())
+        .ok()
+        .or_else(|| parse_decimal(text, number_format))
+        .or_else(|| Decimal::from_str(&number.to_string()).ok())
+}
+
+/// cargo test -- --show-output tests_aggregate
+#[cfg(test)]
+mod tests_aggregate {
+    use super::*;
+    use crate::{analise_line, ColumnLayout};
+    use clap::Parser;
+
+    fn args(options: &[&str]) -> Arguments {
+        Arguments::parse_from(std::iter::once("unique").chain(options.iter().copied()))
+    }
+
+    fn aggregate(options: &[&str], lines: &[&str]) -> UniqueResult<Vec<String>> {
+        let args = args(options);
+        let header: Vec<String> = ["CFOP", "Emissão", "Valor"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let mut aggregation = Aggregation::new(&args, &header)?;
+        for (line_number, line) in lines.iter().enumerate() {
+            let processed = analise_line(line, &ColumnLayout::default(), &args)?;
+            let analyzed = AnalyzedLine {
+                content: processed.content,
+                is_empty: false,
+                values: processed.values,
+                ..AnalyzedLine::empty(line_number + 2)
+            };
+            aggregation.add(&analyzed, &args)?;
+        }
+
+        let mut output = vec![aggregation.header().join(";")];
+        for line in aggregation.lines(&args)? {
+            output.push(line.content);
+        }
+        Ok(output)
+    }
+
+    #[test]
+    fn test_exact_sums_per_group() {
+        let lines = [
+            "5102;01/05/2023;0,10",
+            "6102;03/05/2023;1.000,00",
+            "5102;15/05/2023;0,20",
+            "5102;02/06/2023;",
+        ];
+        let options = [
+            "-c",
+            "--group-by",
+            "CFOP",
+            "--count",
+            "--sum",
+            "Valor",
+            "--min",
+            "Emissão",
+            "--max",
+            "Emissão",
+        ];
+
+        // 0,10 + 0,20 é exatamente 0.30 (em f64 seria 0.30000000000000004)
+        assert_eq!(
+            aggregate(&options, &lines).unwrap(),
+            [
+                "CFOP;count;sum(Valor);min(Emissão);max(Emissão)",
+                "5102;3;0.30;01/05/2023;02/06/2023",
+                "6102;1;1000.00;03/05/2023;03/05/2023",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_by_month() {
+        let lines = [
+            "5102;01/05/2023;10",
+            "6102;31/05/2023;5",
+            "5102;01/06/2023;1",
+        ];
+        let options = ["-c", "--group-by", "Emissão:month", "--sum", "3"];
+
+        assert_eq!(
+            aggregate(&options, &lines).unwrap(),
+            ["month(Emissão);sum(Valor)", "05/2023;15", "06/2023;1"]
+        );
+    }
+
+    #[test]
+    fn test_exact_sums_beyond_f64() {
+        // 17 dígitos significativos: em f64, 12345678901234567,89 seria 12345678901234568
+        let lines = [
+            "5102;01/05/2023;12.345.678.901.234.567,89",
+            "5102;02/05/2023;0,01",
+        ];
+        let options = [
+            "-c",
+            "-f",
+            "--group-by",
+            "CFOP",
+            "--sum",
+            "Valor",
+            "--max",
+            "Valor",
+        ];
+
+        assert_eq!(
+            aggregate(&options, &lines).unwrap(),
+            [
+                "CFOP;sum(Valor);max(Valor)",
+                "5102;12345678901234567.90;12345678901234567.89",
+            ]
+        );
+
+        // A soma tipada (--output-format ndjson, json, parquet) também é exata
+        let sum = TypedValue::Decimal(Decimal::from_str("12345678901234567.90").unwrap());
+        assert_eq!(serde_json::to_string(&sum).unwrap(), "12345678901234567.90");
+    }
+
+    #[test]
+    fn test_international_sums() {
+        let lines = ["5102;01/05/2023;\"1,234.50\"", "5102;02/05/2023;10.25"];
+        let options = ["-c", "--group-by", "CFOP", "--sum", "Valor"];
+
+        let international = [options.as_slice(), &["--number-format", "international"]].concat();
+        assert_eq!(
+            aggregate(&international, &lines).unwrap(),
+            ["CFOP;sum(Valor)", "5102;1244.75"]
+        );
+
+        // Lidos como brasileiros, os separadores não correspondem ao formato
+        let error = aggregate(&options, &lines).unwrap_err();
+        assert!(matches!(
+            error,
+            UniqueError::Aggregation { line_number: 2, ref value, .. } if value == "1,234.50"
+        ));
+    }
+
+    #[test]
+    fn test_sum_of_text_is_an_error() {
+        let lines = ["5102;01/05/2023;10", "5102;02/05/2023;dez"];
+        let options = ["-c", "--group-by", "CFOP", "--sum", "Valor"];
+
+        let error = aggregate(&options, &lines).unwrap_err();
+        assert!(matches!(
+            error,
+            UniqueError::Aggregation { line_number: 3, ref value, .. } if value == "dez"
+        ));
+    }
+}
@@ -4,17 +4,84 @@ use clap::{
         styling::{AnsiColor, Effects},
         Styles,
     },
-    Parser, ValueEnum,
+    ArgGroup, Args, CommandFactory, Parser, Subcommand, ValueEnum,
 };
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::{ffi::OsString, path::PathBuf};
 
-#[derive(Debug, Clone, Copy, ValueEnum, Default)]
+use crate::{
+    is_spreadsheet, parse_output_separator, parse_threshold, FormatRule, HeaderAlias, Separator,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum NumberFormat {
     #[default]
     Brazilian, // Milhar: '.' , Decimal: ','
     International, // Milhar: ',' , Decimal: '.'
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum, Default)]
+pub enum ReportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Default)]
+pub enum StatsFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Default)]
+pub enum OnError {
+    /// Stop at the first line that cannot be processed.
+    #[default]
+    Abort,
+    /// Discard the line and continue.
+    Skip,
+    /// Output the original line unchanged and continue.
+    KeepRaw,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Default)]
+pub enum QuoteStyle {
+    /// Quote only the fields that contain the separator, quotes or line breaks.
+    #[default]
+    Necessary,
+    /// Quote every field.
+    Always,
+    /// Never quote fields.
+    Never,
+    /// Quote every field that is not a number.
+    NonNumeric,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Default)]
+pub enum OutputFormat {
+    /// CSV lines (see --output-separator and --quote-style).
+    #[default]
+    Csv,
+    /// One JSON object per line (JSON Lines).
+    Ndjson,
+    /// A JSON array of objects.
+    Json,
+    /// A Parquet file (binary) with typed columns, one row group per processed chunk.
+    Parquet,
+    /// An Excel workbook (binary) with text, number and date cells.
+    Xlsx,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Normalization {
+    /// Canonical composition: "a" + "◌̃" => "ã"
+    Nfc,
+    /// Compatibility composition: "ﬁ" => "fi", "²" => "2"
+    Nfkc,
+}
+
 /// Custom Clap styling to mimic a beautiful colored help menu.
 fn get_styles() -> Styles {
     let cyan = AnsiColor::Cyan.on_default();
@@ -28,6 +95,53 @@ fn get_styles() -> Styles {
         .placeholder(cyan)
 }
 
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Infer a schema file (see --schema) from the columns of a CSV file
+    /// and print a summary of each column to the standard error.
+    InferSchema(InferSchemaArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct InferSchemaArgs {
+    /// CSV FILE input (or standard input if empty).
+    pub file: Option<PathBuf>,
+
+    /// Set the field separator (delimiter) of the CSV file,
+    /// or 'auto' to detect it from the header and the first rows.
+    #[arg(
+        short('s'),
+        long,
+        default_value_t = Separator::Auto,
+        verbatim_doc_comment
+    )]
+    pub separator: Separator,
+
+    /// Scan only the first N rows (all rows if omitted).
+    #[arg(long, value_name = "N")]
+    pub rows: Option<usize>,
+
+    /// Extra values treated as null (empty), separated by commas.
+    /// The markers found in each column are written to its null_values.
+    /// Example: --null-values '-,0000-00-00,(vazio)'
+    #[arg(
+        long,
+        value_name = "VALUES",
+        value_delimiter = ',',
+        allow_hyphen_values = true,
+        verbatim_doc_comment
+    )]
+    pub null_values: Vec<String>,
+
+    /// Replace the default null list with --null-values instead of extending it
+    #[arg(long, requires("null_values"))]
+    pub replace_null_values: bool,
+
+    /// Write the schema to this file instead of the standard output.
+    #[arg(short('o'), long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
 #[derive(Parser, Debug, Clone)]
 #[command(
     // Read from `Cargo.toml`
@@ -35,16 +149,69 @@ fn get_styles() -> Styles {
     long_about = None,
     next_line_help = true,
     styles=get_styles(),
+    args_conflicts_with_subcommands = true,
+    group(ArgGroup::new("stats_output").args(["verbose", "stats_file"]).multiple(true)),
+    group(
+        ArgGroup::new("number_readers")
+            .args(["format_number", "format_rules", "group_by"])
+            .multiple(true)
+    ),
 )]
 pub struct Arguments {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Parse CSV specifically using the DocsFiscais mapping.
     /// This ensures high robustness by validating column names and data types.
     #[arg(short('m'), long, requires("parse_csv_file"), verbatim_doc_comment)]
     pub map_docs_fiscais: bool,
 
+    /// Map a header name of the CSV file to a DocsFiscais field (with -m).
+    /// Headers are also matched ignoring case, accents and whitespace.
+    /// The option can be repeated. Example: --header-alias 'Valor da Nota=Valor Total : NF (Todos) SOMA'
+    #[arg(
+        long,
+        value_name = "ALIAS=FIELD",
+        requires("map_docs_fiscais"),
+        verbatim_doc_comment
+    )]
+    pub header_alias: Vec<HeaderAlias>,
+
+    /// Fail before processing if these DocsFiscais columns are missing from the header (with -m).
+    /// Column names separated by commas; use '' to require none.
+    /// [default: CNPJ do Contribuinte, Chave da Nota Fiscal Eletrônica and Dia da Emissão]
+    #[arg(
+        long,
+        value_name = "COLUMNS",
+        value_delimiter = ',',
+        requires("map_docs_fiscais"),
+        verbatim_doc_comment
+    )]
+    pub required_columns: Option<Vec<String>>,
+
+    /// Parse CSV using the columns declared in a TOML schema file
+    /// (header name, aliases, type, null values, date and number formats,
+    /// required flag), validating and normalizing each row like -m.
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires("parse_csv_file"),
+        conflicts_with_all(["map_docs_fiscais", "format_rules"]),
+        verbatim_doc_comment
+    )]
+    pub schema: Option<PathBuf>,
+
     /// FILE input (or standard input if empty).
+    /// Spreadsheets (.xlsx, .xlsm, .xlsb, .xls or .ods) are read as CSV files.
+    #[arg(verbatim_doc_comment)]
     pub file: Option<PathBuf>,
 
+    /// Sheet of the spreadsheet FILE, by name or index (starting at 1).
+    /// Default: the first sheet.
+    /// Example: --sheet Itens or --sheet 2
+    #[arg(long, value_name = "NAME|INDEX", verbatim_doc_comment)]
+    pub sheet: Option<String>,
+
     /// Remove empty lines.
     #[arg(short('e'), long)]
     pub remove_empty_lines: bool,
@@ -53,6 +220,105 @@ pub struct Arguments {
     #[arg(short('i'), long)]
     pub ignore_case: bool,
 
+    /// Ignore accents (diacritics) when comparing lines.
+    /// Example: "São Paulo" == "Sao Paulo"
+    #[arg(short('a'), long, verbatim_doc_comment)]
+    pub ignore_accents: bool,
+
+    /// Ignore punctuation and symbols when comparing lines.
+    /// Example: "12.345.678/0001-99" == "12345678000199"
+    /// In CSV files, the column separator is preserved.
+    #[arg(short('p'), long, verbatim_doc_comment)]
+    pub ignore_punctuation: bool,
+
+    /// Ignore punctuation and symbols only in these CSV columns
+    /// (header names or 1-based indexes, separated by commas).
+    /// Example: --ignore-punctuation-columns "CNPJ,CPF/CNPJ do Participante"
+    #[arg(
+        long,
+        value_delimiter = ',',
+        requires("parse_csv_file"),
+        verbatim_doc_comment
+    )]
+    pub ignore_punctuation_columns: Vec<String>,
+
+    /// Output only these CSV columns, in this order
+    /// (header names, 1-based indexes or ranges, separated by commas).
+    /// Example: --columns "CNPJ do Contribuinte : NF Item (Todos),5-8,20-"
+    #[arg(
+        long,
+        value_delimiter = ',',
+        requires("parse_csv_file"),
+        verbatim_doc_comment
+    )]
+    pub columns: Vec<String>,
+
+    /// Remove these CSV columns from the output
+    /// (header names, 1-based indexes or ranges, separated by commas).
+    /// Example: --drop-columns "1-3,Nome do Contribuinte : NF Item (Todos)"
+    #[arg(
+        long,
+        value_delimiter = ',',
+        requires("parse_csv_file"),
+        verbatim_doc_comment
+    )]
+    pub drop_columns: Vec<String>,
+
+    /// Aggregate the unique rows and output one row per group of these
+    /// columns (header names or 1-based indexes, separated by commas),
+    /// in the order the groups first appear.
+    /// A date column followed by ':month' groups by month (MM/YYYY).
+    /// Example: --group-by "CFOP : NF Item (Todos),Dia da Emissão : NF Item (Todos):month"
+    #[arg(
+        long,
+        value_name = "COLUMNS",
+        value_delimiter = ',',
+        requires("parse_csv_file"),
+        conflicts_with("annotate"),
+        verbatim_doc_comment
+    )]
+    pub group_by: Vec<String>,
+
+    /// Sum these columns in each group (exact decimal arithmetic).
+    /// Text values are read in the --number-format style; a value whose
+    /// separators do not match it (e.g. "1,234.50" as brazilian) is an error.
+    /// Example: --sum "Valor Total : NF (Todos) SOMA"
+    #[arg(
+        long,
+        value_name = "COLUMNS",
+        value_delimiter = ',',
+        requires("group_by"),
+        verbatim_doc_comment
+    )]
+    pub sum: Vec<String>,
+
+    /// Count the rows of each group (--group-by)
+    #[arg(long, requires("group_by"))]
+    pub count: bool,
+
+    /// Minimum value (number, date or text) of these columns in each group
+    #[arg(
+        long,
+        value_name = "COLUMNS",
+        value_delimiter = ',',
+        requires("group_by")
+    )]
+    pub min: Vec<String>,
+
+    /// Maximum value (number, date or text) of these columns in each group
+    #[arg(
+        long,
+        value_name = "COLUMNS",
+        value_delimiter = ',',
+        requires("group_by")
+    )]
+    pub max: Vec<String>,
+
+    /// Apply Unicode normalization before comparing lines.
+    /// The output lines are not changed.
+    #[arg(long, value_enum, verbatim_doc_comment)]
+    pub normalize: Option<Normalization>,
+
     /// Returns lines with leading and trailing whitespace removed.
     #[arg(short('t'), long)]
     pub trim_line: bool,
@@ -71,13 +337,119 @@ pub struct Arguments {
     /// semicolon ';'
     /// pipe '|'
     /// or another char.
+    /// Use 'auto' to detect it from the header and the first rows.
     #[arg(
         short('s'),
         long("separator"),
-        default_value_t = ';',
+        default_value_t = Separator::default(),
+        requires("parse_csv_file"),
+        verbatim_doc_comment
+    )]
+    pub separator: Separator,
+
+    /// Set the field separator of the CSV output
+    /// (header and lines). Defaults to the input separator.
+    #[arg(
+        long,
+        value_name = "CHAR",
+        value_parser = parse_output_separator,
+        requires("parse_csv_file"),
+        verbatim_doc_comment
+    )]
+    pub output_separator: Option<char>,
+
+    /// Choose when the fields of the CSV output are quoted.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = QuoteStyle::Necessary,
         requires("parse_csv_file")
     )]
-    pub separator: char,
+    pub quote_style: QuoteStyle,
+
+    /// Choose the format of the processed CSV rows.
+    /// With ndjson or json, each row is an object keyed by the output header,
+    /// with typed values (with -m or --schema): numbers as numbers,
+    /// dates as YYYY-MM-DD and nulls as null.
+    /// With parquet, the columns are typed (Date32, Float64, Int64 or Utf8;
+    /// Decimal128 for the --sum columns).
+    /// With xlsx, keys stay text cells, numbers are numeric cells and dates are date cells.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Csv,
+        requires("parse_csv_file"),
+        verbatim_doc_comment
+    )]
+    pub output_format: OutputFormat,
+
+    /// Extra values treated as null (empty) in CSV files, separated by commas.
+    /// Default list: "", "<N/D>", "N/A", "*DIVERSOS*", "NULO", "NULL".
+    /// Example: --null-values '-,0000-00-00,(vazio)'
+    #[arg(
+        long,
+        value_name = "VALUES",
+        value_delimiter = ',',
+        allow_hyphen_values = true,
+        requires("parse_csv_file"),
+        verbatim_doc_comment
+    )]
+    pub null_values: Vec<String>,
+
+    /// Replace the default null list with --null-values instead of extending it
+    #[arg(long, requires("null_values"))]
+    pub replace_null_values: bool,
+
+    /// Write the null values of the CSV output as TEXT (default: empty).
+    /// Example: --null-output NULL
+    #[arg(
+        long,
+        value_name = "TEXT",
+        allow_hyphen_values = true,
+        requires("parse_csv_file"),
+        verbatim_doc_comment
+    )]
+    pub null_output: Option<String>,
+
+    /// Maximum number of rows shown as examples when the
+    /// column count differs from the header or a line fails.
+    #[arg(long, value_name = "N", default_value_t = 10, verbatim_doc_comment)]
+    pub max_examples: usize,
+
+    /// Write the rows whose column count differs from the header
+    /// to this file and continue processing the valid ones.
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires("parse_csv_file"),
+        verbatim_doc_comment
+    )]
+    pub reject_file: Option<PathBuf>,
+
+    /// What to do with a line that cannot be processed
+    /// (e.g. an invalid number in a column mapped by -m).
+    /// With skip or keep-raw, the errors are summarized at the end.
+    /// With --output-format parquet or xlsx, or with --group-by, keep-raw skips the line.
+    #[arg(long, value_enum, default_value_t = OnError::Abort, verbatim_doc_comment)]
+    pub on_error: OnError,
+
+    /// Write the line errors (line_number;column;value;expected;message)
+    /// to this file (with --on-error skip or keep-raw).
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    pub error_file: Option<PathBuf>,
+
+    /// Format only the CSV columns matching a header pattern
+    /// ('COLUMN:FORMAT', with FORMAT: number, date or nfe-key).
+    /// The pattern accepts globs (*, ?) and the option can be repeated.
+    /// Example: --format 'Valor Total*:number' --format 'Chave*:nfe-key'
+    #[arg(
+        long("format"),
+        value_name = "COLUMN:FORMAT",
+        requires("parse_csv_file"),
+        conflicts_with("map_docs_fiscais"),
+        verbatim_doc_comment
+    )]
+    pub format_rules: Vec<FormatRule>,
 
     /// Formats the date in %d/%m/%Y format in CSV files.
     /// Example:
@@ -97,22 +469,155 @@ pub struct Arguments {
     #[arg(short('f'), long, requires("parse_csv_file"), verbatim_doc_comment)]
     pub format_number: bool,
 
-    /// Choose the number format for parsing decimals and thousands.
+    /// Choose the number format for parsing decimals and thousands
+    /// (with -f, --format 'COLUMN:number' or --group-by).
     #[arg(
         short('n'),
         long,
         value_enum,
         default_value_t = NumberFormat::Brazilian,
-        requires("format_number"),
+        requires("number_readers"),
         verbatim_doc_comment
     )]
     pub number_format: NumberFormat,
 
+    /// Treat similar lines as duplicates (SimHash signatures).
+    /// Lines whose similarity is greater than or equal to the threshold
+    /// (between 0.0 and 1.0) are grouped and only the first one is printed.
+    /// Example: --near-duplicates 0.9
+    #[arg(long, value_name = "THRESHOLD", value_parser = parse_threshold, verbatim_doc_comment)]
+    pub near_duplicates: Option<f64>,
+
+    /// Write the near-duplicate clusters (line numbers, similarity
+    /// and content) to this CSV file.
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires("near_duplicates"),
+        verbatim_doc_comment
+    )]
+    pub cluster_report: Option<PathBuf>,
+
+    /// Report pairs of distinct values of a CSV column whose
+    /// Levenshtein distance is less than or equal to MAX_DISTANCE.
+    /// Useful to build a canonical-name mapping.
+    /// Example: --fuzzy-levenshtein 2 --fuzzy-column "Nome do Participante : NF (Todos)"
+    #[arg(
+        long,
+        value_name = "MAX_DISTANCE",
+        requires("fuzzy_column"),
+        verbatim_doc_comment
+    )]
+    pub fuzzy_levenshtein: Option<usize>,
+
+    /// CSV column (header name or 1-based index) used by --fuzzy-levenshtein.
+    #[arg(
+        long,
+        value_name = "COLUMN",
+        requires_all(["parse_csv_file", "fuzzy_levenshtein"])
+    )]
+    pub fuzzy_column: Option<String>,
+
+    /// Write the --fuzzy-levenshtein pairs to this CSV file
+    /// instead of the standard error.
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires("fuzzy_levenshtein"),
+        verbatim_doc_comment
+    )]
+    pub fuzzy_report: Option<PathBuf>,
+
+    /// Write every repeated key with its number of occurrences
+    /// and original line numbers to this file.
+    /// The main output is not changed.
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    pub report_duplicates: Option<PathBuf>,
+
+    /// Choose the format of the --report-duplicates file.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ReportFormat::Csv,
+        requires("report_duplicates")
+    )]
+    pub report_format: ReportFormat,
+
+    /// Prefix each output line with the source file and the original
+    /// line number ("source:line:content").
+    /// In CSV files, they are added as the first columns.
+    /// With --repeated, the line number of the first occurrence
+    /// matched by the duplicate is also included.
+    #[arg(short('l'), long, verbatim_doc_comment)]
+    pub annotate: bool,
+
     /// Print only duplicate or repeated lines.
     #[arg(short('r'), long("repeated"))]
     pub only_print_repeated_lines: bool,
 
+    /// Validate the input without printing the data.
+    /// Exit codes:
+    /// 0: no problems found
+    /// 1: operation failed (hard errors)
+    /// 2: duplicate lines found
+    /// 3: invalid CSV file (inconsistent column count)
+    /// 4: NF-e keys (44 digits) with invalid check digit
+    /// When several problems are found, the highest code wins.
+    #[arg(long, verbatim_doc_comment)]
+    pub check: bool,
+
     /// Show number of unique, repeated and total lines.
     #[arg(short('v'), long)]
     pub verbose: bool,
+
+    /// Choose the format of the statistics shown by --verbose
+    /// or written to --stats-file (requires one of them).
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = StatsFormat::Text,
+        requires("stats_output"),
+        verbatim_doc_comment
+    )]
+    pub stats_format: StatsFormat,
+
+    /// Write the statistics to this file instead of the standard error.
+    #[arg(long, value_name = "FILE")]
+    pub stats_file: Option<PathBuf>,
+}
+
+impl Arguments {
+    /// Parse the command line arguments.
+    /// A spreadsheet FILE implies --csv, so the options that require it
+    /// (-f, -m, --columns, --output-format, --group-by, ...) are accepted.
+    pub fn parse_args() -> Self {
+        Self::try_parse_args_from(std::env::args_os()).unwrap_or_else(|error| error.exit())
+    }
+
+    /// Same as `parse_args`, from the given arguments.
+    pub fn try_parse_args_from<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let mut args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+
+        // Lenient first pass: only the FILE argument is needed here
+        let matches = Self::command()
+            .ignore_errors(true)
+            .try_get_matches_from(args.clone())?;
+
+        let spreadsheet = matches.subcommand().is_none()
+            && matches
+                .try_get_one::<PathBuf>("file")
+                .ok()
+                .flatten()
+                .is_some_and(|path| is_spreadsheet(path));
+
+        if spreadsheet && !matches.get_flag("parse_csv_file") && !args.is_empty() {
+            args.insert(1, "--csv".into());
+        }
+
+        Self::try_parse_from(args)
+    }
 }
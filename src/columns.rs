@@ -0,0 +1,172 @@
+use crate::{UniqueError, UniqueResult};
+use std::collections::HashSet;
+
+/// Resolve uma lista de colunas (nomes do cabeçalho ou índices iniciados em 1)
+/// para as posições (iniciadas em 0) correspondentes no cabeçalho.
+///
+/// Example:
+/// ```
+/// use unique::resolve_columns;
+/// let header = vec!["CNPJ".to_string(), "Nome".to_string(), "Valor".to_string()];
+/// let specs = vec!["Valor".to_string(), "1".to_string()];
+/// assert_eq!(resolve_columns(&specs, &header).unwrap(), vec![2, 0]);
+/// ```
+pub fn resolve_columns(specs: &[String], header: &[String]) -> UniqueResult<Vec<usize>> {
+    specs
+        .iter()
+        .map(|spec| resolve_column(spec, header))
+        .collect()
+}
+
+/// Resolve as colunas da saída (`--columns` e `--drop-columns`).
+///
+/// As colunas selecionadas mantêm a ordem informada; na ausência de seleção,
+/// todas as colunas do cabeçalho são mantidas. As colunas removidas são
+/// retiradas em seguida. Além de nomes e índices, são aceitos intervalos de
+/// índices: "2-5", "3-" (até a última) e "-4" (desde a primeira).
+///
+/// Retorna `None` quando nenhuma das opções é informada.
+///
+/// Example:
+/// ```
+/// use unique::resolve_projection;
+/// let header: Vec<String> = ["A", "B", "C", "D", "E"].iter().map(|s| s.to_string()).collect();
+/// let columns = vec!["E".to_string(), "1-3".to_string()];
+/// let drop = vec!["B".to_string()];
+/// assert_eq!(resolve_projection(&columns, &drop, &header).unwrap(), Some(vec![4, 0, 2]));
+/// ```
+pub fn resolve_projection(
+    columns: &[String],
+    drop_columns: &[String],
+    header: &[String],
+) -> UniqueResult<Option<Vec<usize>>> {
+    if columns.is_empty() && drop_columns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut selected: Vec<usize> = if columns.is_empty() {
+        (0..header.len()).collect()
+    } else {
+        expand_columns(columns, header)?
+    };
+
+    let dropped: HashSet<usize> = expand_columns(drop_columns, header)?.into_iter().collect();
+    selected.retain(|index| !dropped.contains(index));
+
+    Ok(Some(selected))
+}
+
+/// Resolve nomes, índices e intervalos de índices em posições do cabeçalho.
+fn expand_columns(specs: &[String], header: &[String]) -> UniqueResult<Vec<usize>> {
+    let mut indexes = Vec::new();
+
+    for spec in specs {
+        match parse_range(spec, header) {
+            Some(range) => indexes.extend(range?),
+            None => indexes.push(resolve_column(spec, header)?),
+        }
+    }
+
+    Ok(indexes)
+}
+
+/// Interpreta um intervalo de índices ("2-5", "3-" ou "-4").
+///
+/// Retorna `None` se a especificação não for um intervalo
+/// ou se for o nome de uma coluna do cabeçalho.
+fn parse_range(spec: &str, header: &[String]) -> Option<UniqueResult<std::ops::Range<usize>>> {
+    let spec = spec.trim();
+    if header.iter().any(|name| name.trim() == spec) {
+        return None;
+    }
+
+    let (start, end) = spec.split_once('-')?;
+    let is_index = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if !is_index(start) || !is_index(end) || (start.is_empty() && end.is_empty()) {
+        return None;
+    }
+
+    let start: usize = start.parse().unwrap_or(1);
+    let end: usize = end.parse().unwrap_or(header.len());
+
+    Some(if 1 <= start && start <= end && end <= header.len() {
+        Ok(start - 1..end)
+    } else {
+        Err(UniqueError::ColumnNotFound(spec.to_string()))
+    })
+}
+
+/// Resolve uma única coluna: primeiro pelo nome exato, depois pelo índice.
+fn resolve_column(spec: &str, header: &[String]) -> UniqueResult<usize> {
+    let spec = spec.trim();
+
+    if let Some(index) = header.iter().position(|name| name.trim() == spec) {
+        return Ok(index);
+    }
+
+    match spec.parse::<usize>() {
+        Ok(index) if (1..=header.len()).contains(&index) => Ok(index - 1),
+        _ => Err(UniqueError::ColumnNotFound(spec.to_string())),
+    }
+}
+
+/// cargo test -- --show-output tests_columns
+#[cfg(test)]
+mod tests_columns {
+    use super::*;
+
+    fn header() -> Vec<String> {
+        ["CNPJ", "Nome", "2024", "Valor"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    fn specs(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_resolve_by_name_and_index() {
+        let result = resolve_columns(&specs(&["Nome", "4", " CNPJ "]), &header()).unwrap();
+        assert_eq!(result, vec![1, 3, 0]);
+    }
+
+    #[test]
+    fn test_name_has_priority_over_index() {
+        // A coluna "2024" existe no cabeçalho e não deve ser lida como índice
+        let result = resolve_columns(&specs(&["2024"]), &header()).unwrap();
+        assert_eq!(result, vec![2]);
+    }
+
+    #[test]
+    fn test_projection_with_ranges() {
+        let header = header();
+        let projection = |columns: &[&str], drop: &[&str]| {
+            resolve_projection(&specs(columns), &specs(drop), &header)
+        };
+
+        assert_eq!(projection(&[], &[]).unwrap(), None);
+        assert_eq!(projection(&["2-3"], &[]).unwrap(), Some(vec![1, 2]));
+        assert_eq!(
+            projection(&["Valor", "-2"], &[]).unwrap(),
+            Some(vec![3, 0, 1])
+        );
+        assert_eq!(projection(&[], &["3-"]).unwrap(), Some(vec![0, 1]));
+        assert_eq!(
+            projection(&["1-4"], &["Nome", "1"]).unwrap(),
+            Some(vec![2, 3])
+        );
+
+        assert!(projection(&["3-2"], &[]).is_err());
+        assert!(projection(&["2-9"], &[]).is_err());
+        assert!(projection(&[], &["Data"]).is_err());
+    }
+
+    #[test]
+    fn test_unknown_columns() {
+        assert!(resolve_columns(&specs(&["Data"]), &header()).is_err());
+        assert!(resolve_columns(&specs(&["0"]), &header()).is_err());
+        assert!(resolve_columns(&specs(&["5"]), &header()).is_err());
+    }
+}
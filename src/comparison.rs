@@ -0,0 +1,185 @@
+use crate::{split_fields, Arguments, Normalization, UniqueResult};
+use claudiofsr_lib::StrExtension;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Separates the CSV fields in a comparison key (ASCII Unit Separator).
+const KEY_FIELD_SEPARATOR: &str = "\u{1f}";
+
+/// Builds the key used to compare lines during deduplication.
+///
+/// The key is derived from the processed content but never replaces it:
+/// the output keeps the original text while the comparison may ignore
+/// case, accents, punctuation or Unicode representation differences.
+///
+/// `punctuation_columns` holds the 0-based CSV columns whose punctuation
+/// must be ignored (see `resolve_columns`).
+pub fn comparison_key(
+    content: &str,
+    args: &Arguments,
+    punctuation_columns: &[usize],
+) -> UniqueResult<String> {
+    let key: String =
+        if args.parse_csv_file && (args.ignore_punctuation || !punctuation_columns.is_empty()) {
+            // Remove punctuation field by field, keeping the columns apart
+            split_fields(content, args)?
+                .iter()
+                .enumerate()
+                .map(|(index, field)| {
+                    if args.ignore_punctuation || punctuation_columns.contains(&index) {
+                        remove_punctuation(field)
+                    } else {
+                        field.to_string()
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(KEY_FIELD_SEPARATOR)
+        } else if args.ignore_punctuation {
+            remove_punctuation(content)
+        } else {
+            content.to_string()
+        };
+
+    Ok(normalize_key(key, args))
+}
+
+/// Applies the Unicode normalization, accent and case options to a key.
+pub fn normalize_key(key: String, args: &Arguments) -> String {
+    let mut key: String = match args.normalize {
+        Some(Normalization::Nfc) => key.nfc().collect(),
+        Some(Normalization::Nfkc) => key.nfkc().collect(),
+        None => key,
+    };
+
+    if args.ignore_accents {
+        key = remove_accents(&key);
+    }
+    if args.ignore_case {
+        key = key.to_lowercase();
+    }
+    key
+}
+
+/// Remove pontuação e símbolos, preservando letras, dígitos e espaços.
+///
+/// Palavras sem letras (CNPJ, CPF, valores) ficam apenas com os dígitos,
+/// como em `check_cnpj`; nas demais, letras e dígitos são preservados.
+///
+/// Example:
+/// ```
+/// use unique::remove_punctuation;
+/// assert_eq!(remove_punctuation("12.345.678/0001-99"), "12345678000199");
+/// assert_eq!(remove_punctuation("R$ 1.500,00 (total)"), "R 150000 total");
+/// ```
+pub fn remove_punctuation(text: &str) -> String {
+    text.split_inclusive(char::is_whitespace)
+        .map(|word| {
+            // O espaço final da palavra é mantido
+            let (word, space) = word.split_at(word.trim_end().len());
+            if word.contains_some_digits() && !word.chars().any(char::is_alphabetic) {
+                [word.remove_non_digits().as_str(), space].concat()
+            } else {
+                word.chars()
+                    .filter(|c| c.is_alphanumeric())
+                    .chain(space.chars())
+                    .collect()
+            }
+        })
+        .collect()
+}
+
+/// Remove os diacríticos (acentos) de um texto.
+///
+/// O texto é decomposto (NFD), as marcas combinantes são descartadas
+/// e o resultado é recomposto (NFC).
+///
+/// Example:
+/// ```
+/// use unique::remove_accents;
+/// assert_eq!(remove_accents("São Paulo"), "Sao Paulo");
+/// assert_eq!(remove_accents("Açúcar, Pão e Café"), "Acucar, Pao e Cafe");
+/// ```
+pub fn remove_accents(text: &str) -> String {
+    text.nfd()
+        .filter(|&c| !is_combining_mark(c))
+        .nfc()
+        .collect()
+}
+
+/// cargo test -- --show-output tests_comparison
+#[cfg(test)]
+mod tests_comparison {
+    use super::*;
+    use clap::Parser;
+
+    const COMPOSED: &str = "S\u{e3}o Paulo"; // 'ã' pré-composto
+    const DECOMPOSED: &str = "Sa\u{303}o Paulo"; // 'a' + til combinante
+
+    fn args(options: &[&str]) -> Arguments {
+        Arguments::parse_from(std::iter::once("unique").chain(options.iter().copied()))
+    }
+
+    fn key(content: &str, args: &Arguments) -> String {
+        comparison_key(content, args, &[]).unwrap()
+    }
+
+    #[test]
+    fn test_default_key_is_unchanged() {
+        let a = args(&[]);
+        assert_eq!(key(COMPOSED, &a), COMPOSED);
+        assert_ne!(key(COMPOSED, &a), key(DECOMPOSED, &a));
+    }
+
+    #[test]
+    fn test_normalize_nfc() {
+        let a = args(&["--normalize", "nfc"]);
+        assert_eq!(key(COMPOSED, &a), key(DECOMPOSED, &a));
+        assert_ne!(key(COMPOSED, &a), key("Sao Paulo", &a));
+    }
+
+    #[test]
+    fn test_normalize_nfkc() {
+        let a = args(&["--normalize", "nfkc"]);
+        assert_eq!(key("\u{fb01}le", &a), "file");
+        assert_eq!(key("m\u{b2}", &a), "m2");
+    }
+
+    #[test]
+    fn test_ignore_accents() {
+        let a = args(&["--ignore-accents"]);
+        assert_eq!(key(COMPOSED, &a), "Sao Paulo");
+        assert_eq!(key(DECOMPOSED, &a), "Sao Paulo");
+    }
+
+    #[test]
+    fn test_ignore_accents_and_case() {
+        let a = args(&["-a", "-i"]);
+        assert_eq!(key("AÇÚCAR", &a), key("açucar", &a));
+        assert_eq!(key("AÇÚCAR", &a), "acucar");
+    }
+
+    #[test]
+    fn test_ignore_punctuation() {
+        let a = args(&["-p"]);
+        assert_eq!(key("12.345.678/0001-99", &a), key("12345678000199", &a));
+        assert_ne!(key("12.345.678/0001-99", &a), key("12345678000198", &a));
+    }
+
+    #[test]
+    fn test_ignore_punctuation_keeps_csv_columns() {
+        let a = args(&["--csv", "-p"]);
+        // "1;23" e "12;3" não podem gerar a mesma chave
+        assert_ne!(key("1;23", &a), key("12;3", &a));
+        assert_eq!(key("12.345/0001-99;A", &a), key("12345000199;A", &a));
+    }
+
+    #[test]
+    fn test_ignore_punctuation_by_column() {
+        let a = args(&["--csv", "--ignore-punctuation-columns", "1"]);
+        let columns = [0];
+        let k1 = comparison_key("12.345/0001-99;Empresa S.A.", &a, &columns).unwrap();
+        let k2 = comparison_key("12345000199;Empresa S.A.", &a, &columns).unwrap();
+        let k3 = comparison_key("12345000199;Empresa SA", &a, &columns).unwrap();
+        assert_eq!(k1, k2);
+        assert_ne!(k2, k3);
+    }
+}
@@ -1,8 +1,23 @@
-use crate::{format_number, NumberFormat};
+use crate::{format_number, ColumnType, NumberFormat, TypedValue, UniqueError, UniqueResult};
 use chrono::NaiveDate;
+use csv::{ReaderBuilder, StringRecord, WriterBuilder};
 use serde::{self, de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::HashMap,
+    sync::{LazyLock as Lazy, OnceLock},
+};
 
-const DATA_FORMAT: &str = "%d/%m/%Y";
+pub const DATA_FORMAT: &str = "%d/%m/%Y";
+
+/// Valores tratados como nulos (comparação sem distinção de maiúsculas).
+pub const NULL_VALUES: [&str; 6] = ["", "<N/D>", "N/A", "*DIVERSOS*", "NULO", "NULL"];
+
+/// Colunas obrigatórias do modo `-m` (padrão de `--required-columns`).
+pub const REQUIRED_COLUMNS: [&str; 3] = [
+    "CNPJ do Contribuinte : NF Item (Todos)",
+    "Chave da Nota Fiscal Eletrônica : NF Item (Todos)",
+    "Dia da Emissão : NF Item (Todos)",
+];
 
 // Adicionar #[serde(default)] no topo da struct. Isso diz ao Rust:
 // "Se uma coluna não existir no arquivo, preencha o campo com o valor padrão (String vazia, 0 ou None)".
@@ -263,18 +278,202 @@ pub struct DocsFiscais {
     pub valor_icms_sub: Option<f64>,
 }
 
+impl DocsFiscais {
+    /// Retorna os nomes das colunas (`rename` do Serde) na ordem de serialização da struct.
+    ///
+    /// É o cabeçalho correspondente às linhas produzidas por `analise_line_with_serde`.
+    pub fn headers() -> UniqueResult<Vec<String>> {
+        let mut wtr = WriterBuilder::new().has_headers(true).from_writer(vec![]);
+
+        wtr.serialize(DocsFiscais::default())?;
+        let bytes = wtr.into_inner().map_err(|e| e.into_error())?;
+
+        let mut reader = ReaderBuilder::new()
+            .has_headers(true)
+            .from_reader(bytes.as_slice());
+
+        Ok(reader.headers()?.iter().map(String::from).collect())
+    }
+
+    /// Retorna os tipos das colunas, na ordem de `headers`.
+    pub fn column_types() -> UniqueResult<Vec<ColumnType>> {
+        Ok(Self::expected_types()?
+            .into_iter()
+            .map(|expected| expected.map_or(ColumnType::String, ColumnType::from))
+            .collect())
+    }
+
+    /// Retorna o tipo esperado de cada coluna, na ordem de `headers`
+    /// (`None` nas colunas de texto).
+    ///
+    /// O tipo é identificado pelos valores que cada coluna aceita:
+    /// um texto qualquer, uma data, um número com casas decimais.
+    pub fn expected_types() -> UniqueResult<Vec<Option<ExpectedType>>> {
+        let accepts = |header: &StringRecord, value: &str| {
+            StringRecord::from(vec![value])
+                .deserialize::<DocsFiscais>(Some(header))
+                .is_ok()
+        };
+
+        let types = Self::headers()?
+            .iter()
+            .map(|name| {
+                let header = StringRecord::from(vec![name.as_str()]);
+                if accepts(&header, "?") {
+                    None
+                } else if accepts(&header, "01/01/2000") {
+                    Some(ExpectedType::Date)
+                } else if accepts(&header, "1,5") {
+                    Some(ExpectedType::F64)
+                } else {
+                    Some(ExpectedType::I64)
+                }
+            })
+            .collect();
+
+        Ok(types)
+    }
+
+    /// Tipo esperado da coluna `name` (`None` nas colunas de texto ou desconhecidas).
+    pub fn expected_type(name: &str) -> Option<ExpectedType> {
+        static TYPES: Lazy<HashMap<String, ExpectedType>> = Lazy::new(|| {
+            let headers = DocsFiscais::headers().expect("DocsFiscais::headers() inválido!");
+            let types =
+                DocsFiscais::expected_types().expect("DocsFiscais::expected_types() inválido!");
+            headers
+                .into_iter()
+                .zip(types)
+                .filter_map(|(name, expected)| Some((name, expected?)))
+                .collect()
+        });
+
+        TYPES.get(name).copied()
+    }
+
+    /// Retorna os valores tipados dos campos, na ordem de `headers`.
+    ///
+    /// Os números mantêm o tipo, a data de emissão é uma data e os
+    /// textos nulos (ver `is_null_val`) são `TypedValue::Null`.
+    pub fn typed_values(&self) -> UniqueResult<Vec<TypedValue>> {
+        static HEADERS: Lazy<Vec<String>> =
+            Lazy::new(|| DocsFiscais::headers().expect("DocsFiscais::headers() inválido!"));
+
+        let serde_json::Value::Object(mut fields) =
+            serde_json::to_value(self).map_err(|e| UniqueError::Unknown(e.to_string()))?
+        else {
+            return Err(UniqueError::Unknown(
+                "DocsFiscais não é um objeto".to_string(),
+            ));
+        };
+
+        let values = HEADERS
+            .iter()
+            .map(|name| match fields.remove(name) {
+                _ if name == "Dia da Emissão : NF Item (Todos)" => {
+                    self.dia_emissao.map_or(TypedValue::Null, TypedValue::Date)
+                }
+                Some(serde_json::Value::Number(n)) => match n.as_i64() {
+                    Some(i) => TypedValue::Integer(i),
+                    None => n.as_f64().map_or(TypedValue::Null, TypedValue::Float),
+                },
+                Some(serde_json::Value::String(s)) if !is_null_val(&s) => TypedValue::Text(s),
+                Some(serde_json::Value::Bool(b)) => TypedValue::Text(b.to_string()),
+                _ => TypedValue::Null,
+            })
+            .collect();
+
+        Ok(values)
+    }
+}
+
+/// Tipo esperado em uma coluna tipada de `DocsFiscais`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExpectedType {
+    /// Data no formato DD/MM/AAAA.
+    Date,
+    F64,
+    I64,
+}
+
+impl ExpectedType {
+    /// Mensagem de erro dos deserializadores de cada tipo.
+    fn message(self) -> &'static str {
+        match self {
+            ExpectedType::Date => "Data inválida",
+            ExpectedType::F64 => "Float inválido",
+            ExpectedType::I64 => "Inteiro inválido",
+        }
+    }
+
+    /// Indica se o texto é um valor válido (ou nulo) deste tipo,
+    /// com as mesmas regras dos deserializadores de `DocsFiscais`.
+    pub fn accepts(self, value: &str) -> bool {
+        if is_null_val(value) {
+            return true;
+        }
+        match self {
+            ExpectedType::Date => value.trim().is_empty() || parse_br_date(value).is_some(),
+            ExpectedType::F64 => parse_f64(value).is_some(),
+            ExpectedType::I64 => parse_i64(value).is_some(),
+        }
+    }
+}
+
+impl std::fmt::Display for ExpectedType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ExpectedType::Date => "date",
+            ExpectedType::F64 => "f64",
+            ExpectedType::I64 => "i64",
+        };
+        write!(f, "{name}")
+    }
+}
+
 // --- DESERIALIZADORES PARA OPTION ---
 
+/// Valores nulos em uso (definidos uma única vez por `set_null_values`).
+static NULL_MARKERS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Define os valores tratados como nulos (`--null-values`).
+///
+/// Deve ser chamada antes do processamento das linhas: a lista é global
+/// porque os deserializadores do Serde não recebem os argumentos.
+/// Chamadas posteriores não têm efeito.
+pub fn set_null_values(values: Vec<String>) {
+    let _ = NULL_MARKERS.set(values);
+}
+
+/// Valores tratados como nulos (padrão: `NULL_VALUES`).
+pub fn null_values() -> &'static [String] {
+    NULL_MARKERS.get_or_init(|| NULL_VALUES.iter().map(|s| s.to_string()).collect())
+}
+
 /// Helper para identificar valores que devem ser tratados como nulos
-fn is_null_val(s: &str) -> bool {
+pub fn is_null_val(s: &str) -> bool {
     // 1. Removemos espaços
     let s = s.trim();
 
-    // 2. Definimos os padrões apenas em maiúsculo
-    let null_patterns = ["", "<N/D>", "N/A", "*DIVERSOS*", "NULO", "NULL"];
+    // 2. Verificamos se o valor normalizado está na lista
+    null_values()
+        .iter()
+        .any(|p| p.trim().eq_ignore_ascii_case(s))
+}
 
-    // 3. Verificamos se o valor normalizado está na lista
-    null_patterns.iter().any(|&p| p.eq_ignore_ascii_case(s))
+/// Número no formato brasileiro (ver `format_number`).
+fn parse_f64(val: &str) -> Option<f64> {
+    format_number(val, NumberFormat::Brazilian).parse().ok()
+}
+
+/// Inteiro, ignorando os pontos de milhar.
+fn parse_i64(val: &str) -> Option<i64> {
+    val.trim().replace('.', "").parse().ok()
+}
+
+/// Data no formato DD/MM/AAAA.
+fn parse_br_date(val: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(val.trim(), DATA_FORMAT).ok()
 }
 
 pub fn string_as_opt_f64<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
@@ -283,13 +482,9 @@ where
 {
     let s: Option<String> = Option::deserialize(deserializer)?;
     match s {
-        Some(val) if !is_null_val(&val) => {
-            let cleaned = format_number(&val, NumberFormat::Brazilian);
-            cleaned
-                .parse::<f64>()
-                .map(Some)
-                .map_err(|_| Error::custom(format!("Float inválido: '{}'", val)))
-        }
+        Some(val) if !is_null_val(&val) => parse_f64(&val)
+            .map(Some)
+            .ok_or_else(|| Error::custom(format!("{}: '{}'", ExpectedType::F64.message(), val))),
         _ => Ok(None),
     }
 }
@@ -300,13 +495,9 @@ where
 {
     let s: Option<String> = Option::deserialize(deserializer)?;
     match s {
-        Some(val) if !is_null_val(&val) => {
-            let cleaned = val.trim().replace('.', "");
-            cleaned
-                .parse::<i64>()
-                .map(Some)
-                .map_err(|_| Error::custom(format!("Inteiro inválido: '{}'", val)))
-        }
+        Some(val) if !is_null_val(&val) => parse_i64(&val)
+            .map(Some)
+            .ok_or_else(|| Error::custom(format!("{}: '{}'", ExpectedType::I64.message(), val))),
         _ => Ok(None),
     }
 }
@@ -331,9 +522,9 @@ mod br_date_opt {
     {
         let opt = Option::<String>::deserialize(deserializer)?;
         match opt {
-            Some(s) if !s.trim().is_empty() => NaiveDate::parse_from_str(s.trim(), DATA_FORMAT)
+            Some(s) if !s.trim().is_empty() && !is_null_val(&s) => parse_br_date(&s)
                 .map(Some)
-                .map_err(|_| Error::custom(format!("Data inválida: {}", s))),
+                .ok_or_else(|| Error::custom(format!("{}: {}", ExpectedType::Date.message(), s))),
             _ => Ok(None),
         }
     }
@@ -344,7 +535,6 @@ mod br_date_opt {
 mod tests_docs_fiscais {
     use super::*;
     use chrono::NaiveDate;
-    use csv::ReaderBuilder;
 
     /// Teste para a lógica de identificação de valores nulos/vazios
     #[test]
@@ -398,6 +588,28 @@ CNPJ do Contribuinte : NF Item (Todos);CRT : NF (Todos);Número da Nota : NF Ite
         // Validação de Option<f64> (formato brasileiro)
         assert_eq!(doc.valor_total, Some(1500.50));
         assert_eq!(doc.aliq_icms, Some(18.00));
+
+        // Valores tipados (--output-format ndjson|json)
+        let headers = DocsFiscais::headers().unwrap();
+        let values = doc.typed_values().unwrap();
+        let value = |name: &str| &values[headers.iter().position(|h| h == name).unwrap()];
+        assert_eq!(values.len(), headers.len());
+        assert_eq!(
+            value("Dia da Emissão : NF Item (Todos)"),
+            &TypedValue::Date(NaiveDate::from_ymd_opt(2023, 12, 25).unwrap())
+        );
+        assert_eq!(
+            value("Número da Nota : NF Item (Todos)"),
+            &TypedValue::Integer(123456)
+        );
+        assert_eq!(
+            value("Valor Total : NF (Todos) SOMA"),
+            &TypedValue::Float(1500.5)
+        );
+        assert_eq!(
+            value("Nome do Contribuinte : NF Item (Todos)"),
+            &TypedValue::Null
+        );
     }
 
     /// Teste específico para casos de erro e valores nulos no CSV
@@ -421,6 +633,19 @@ Empresa Teste;N/A;<N/D>; ;NULO";
         assert_eq!(doc.valor_total, None);
     }
 
+    /// O cabeçalho deve seguir a ordem dos campos da struct
+    #[test]
+    fn test_docs_fiscais_headers() {
+        let headers = DocsFiscais::headers().unwrap();
+
+        assert_eq!(headers.len(), 56);
+        assert_eq!(headers[0], "CNPJ do Contribuinte : NF Item (Todos)");
+        assert_eq!(
+            headers[55],
+            "ICMS por Substituição: Valor do Tributo : NF Item (Todos) SOMA"
+        );
+    }
+
     /// Teste isolado para o conversor de data BR
     #[test]
     fn test_br_date_deserializer() {
@@ -451,4 +676,34 @@ Empresa Teste;N/A;<N/D>; ;NULO";
         let res: Result<DateWrapper, _> = serde_json::from_str(json_err);
         assert!(res.is_err());
     }
+
+    #[test]
+    fn test_expected_types() {
+        let headers = DocsFiscais::headers().unwrap();
+        let types = DocsFiscais::expected_types().unwrap();
+        let expected = |name: &str| types[headers.iter().position(|h| h == name).unwrap()];
+
+        assert_eq!(expected("CNPJ do Contribuinte : NF Item (Todos)"), None);
+        assert_eq!(
+            expected("Dia da Emissão : NF Item (Todos)"),
+            Some(ExpectedType::Date)
+        );
+        assert_eq!(
+            expected("Valor Total : NF (Todos) SOMA"),
+            Some(ExpectedType::F64)
+        );
+        assert_eq!(
+            DocsFiscais::expected_type("Valor Total : NF (Todos) SOMA"),
+            Some(ExpectedType::F64)
+        );
+        assert_eq!(DocsFiscais::expected_type("outra coluna"), None);
+
+        assert!(ExpectedType::F64.accepts("1.234,56"));
+        assert!(ExpectedType::F64.accepts("N/A"));
+        assert!(!ExpectedType::F64.accepts("12,3x"));
+        assert!(ExpectedType::Date.accepts(""));
+        assert!(!ExpectedType::Date.accepts("32/01/2024"));
+        assert!(!ExpectedType::I64.accepts("1,5"));
+        assert_eq!(ExpectedType::I64.to_string(), "i64");
+    }
 }
@@ -1,3 +1,5 @@
+use crate::ExpectedType;
+use serde::Serialize;
 use thiserror::Error;
 
 pub type UniqueResult<T> = Result<T, UniqueError>;
@@ -19,6 +21,15 @@ pub enum UniqueError {
     #[error("Erro no processamento de CSV: {0}")]
     Csv(#[from] csv::Error),
 
+    #[error("Erro na gravação do arquivo Parquet: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+
+    #[error("Erro na gravação do arquivo XLSX: {0}")]
+    Xlsx(#[from] rust_xlsxwriter::XlsxError),
+
+    #[error("Erro na montagem dos dados Arrow: {0}")]
+    Arrow(#[from] arrow_schema::ArrowError),
+
     #[error("Falha na conversão UTF-8: {0}")]
     Utf8(#[from] std::string::FromUtf8Error),
 
@@ -31,9 +42,83 @@ pub enum UniqueError {
     #[error("Erro de mapeamento de colunas: {0}")]
     Mapping(String),
 
+    #[error("Erro de mapeamento na linha {line_number}, coluna '{column}': valor '{value}' inválido (esperado: {expected})")]
+    FieldMapping {
+        line_number: usize,
+        column: String,
+        value: String,
+        expected: ExpectedType,
+    },
+
+    #[error("Erro na linha {line_number}: {source}")]
+    Line {
+        line_number: usize,
+        source: Box<UniqueError>,
+    },
+
+    #[error("Erro no esquema '{path}': {message}")]
+    Schema { path: String, message: String },
+
+    #[error("Erro na planilha '{path}': {message}")]
+    Spreadsheet { path: String, message: String },
+
+    #[error(
+        "Erro na linha {line_number}, coluna '{column}': valor '{value}' não numérico no formato de --number-format (--sum)"
+    )]
+    Aggregation {
+        line_number: usize,
+        column: String,
+        value: String,
+    },
+
+    #[error("Colunas obrigatórias ausentes no cabeçalho:{0}")]
+    MissingColumns(String),
+
+    #[error("Coluna não encontrada no cabeçalho: '{0}'")]
+    ColumnNotFound(String),
+
     #[error("Falha ao converter '{0}' para número (f64)")]
     ParseFloat(String),
 
     #[error("Erro desconhecido: {0}")]
     Unknown(String),
 }
+
+/// Erro ocorrido no processamento de uma linha, registrado com `--on-error`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LineError {
+    pub line_number: usize,
+    /// Nome da coluna, quando o erro pode ser atribuído a uma coluna.
+    pub column: Option<String>,
+    /// Valor original da coluna.
+    pub value: Option<String>,
+    /// Tipo esperado na coluna.
+    pub expected: Option<ExpectedType>,
+    pub message: String,
+}
+
+impl LineError {
+    pub fn new(line_number: usize, error: &UniqueError) -> Self {
+        match error {
+            UniqueError::FieldMapping {
+                column,
+                value,
+                expected,
+                ..
+            } => Self {
+                line_number,
+                column: Some(column.clone()),
+                value: Some(value.clone()),
+                expected: Some(*expected),
+                message: format!("Valor inválido para o tipo {expected}"),
+            },
+            other => Self {
+                line_number,
+                column: None,
+                value: None,
+                expected: None,
+                message: other.to_string(),
+            },
+        }
+    }
+}
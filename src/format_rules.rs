@@ -0,0 +1,198 @@
+use crate::{UniqueError, UniqueResult};
+use globset::{Glob, GlobMatcher};
+use std::str::FromStr;
+
+/// Formatação aplicada às células de uma coluna.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatKind {
+    /// Número no formato computacional (ver `format_number`).
+    Number,
+    /// Data no formato DD/MM/AAAA (ver `format_date`).
+    Date,
+    /// Chave de NF-e de 44 dígitos (ver `format_key`).
+    NfeKey,
+}
+
+impl FromStr for FormatKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "number" => Ok(FormatKind::Number),
+            "date" => Ok(FormatKind::Date),
+            "nfe-key" => Ok(FormatKind::NfeKey),
+            other => Err(format!(
+                "unknown format '{other}' (possible values: number, date, nfe-key)"
+            )),
+        }
+    }
+}
+
+/// Regra de formatação por coluna (`--format 'Coluna:tipo'`).
+///
+/// O padrão é o nome da coluna no cabeçalho e aceita curingas (glob):
+/// `*` (qualquer sequência), `?` (um caractere) e `[...]` (classe).
+#[derive(Debug, Clone)]
+pub struct FormatRule {
+    pub pattern: String,
+    pub kind: FormatKind,
+    matcher: GlobMatcher,
+}
+
+impl FormatRule {
+    /// Indica se a regra se aplica à coluna.
+    pub fn matches(&self, column: &str) -> bool {
+        self.matcher.is_match(column.trim())
+    }
+}
+
+impl FromStr for FormatRule {
+    type Err = String;
+
+    /// Interpreta "Padrão:tipo". Como os nomes das colunas podem conter ':',
+    /// o tipo é o texto após o último ':'.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (pattern, kind) = value
+            .rsplit_once(':')
+            .ok_or_else(|| format!("'{value}' must be in the form 'COLUMN:FORMAT'"))?;
+
+        let pattern = pattern.trim().to_string();
+        if pattern.is_empty() {
+            return Err(format!("'{value}' has an empty column pattern"));
+        }
+
+        let matcher = Glob::new(&pattern)
+            .map_err(|e| format!("invalid column pattern '{pattern}': {e}"))?
+            .compile_matcher();
+
+        Ok(Self {
+            pattern,
+            kind: kind.parse()?,
+            matcher,
+        })
+    }
+}
+
+/// Resolve as regras de formatação para cada coluna do cabeçalho.
+///
+/// Retorna, para cada coluna, as formatações aplicáveis. Uma regra que
+/// não corresponde a nenhuma coluna é um erro (provável erro de digitação).
+///
+/// Example:
+/// ```
+/// use unique::{resolve_formats, FormatKind, FormatRule};
+/// let header = vec!["CFOP".to_string(), "Valor Total".to_string(), "Valor Item".to_string()];
+/// let rules: Vec<FormatRule> = vec!["Valor*:number".parse().unwrap()];
+/// let formats = resolve_formats(&rules, &header).unwrap();
+/// assert!(formats[0].is_empty());
+/// assert_eq!(formats[1], vec![FormatKind::Number]);
+/// assert_eq!(formats[2], vec![FormatKind::Number]);
+/// ```
+pub fn resolve_formats(
+    rules: &[FormatRule],
+    header: &[String],
+) -> UniqueResult<Vec<Vec<FormatKind>>> {
+    let mut formats: Vec<Vec<FormatKind>> = vec![Vec::new(); header.len()];
+
+    for rule in rules {
+        let mut found = false;
+        for (column, kinds) in header.iter().zip(formats.iter_mut()) {
+            if rule.matches(column) {
+                found = true;
+                if !kinds.contains(&rule.kind) {
+                    kinds.push(rule.kind);
+                }
+            }
+        }
+        if !found {
+            return Err(UniqueError::ColumnNotFound(rule.pattern.clone()));
+        }
+    }
+
+    Ok(formats)
+}
+
+/// cargo test -- --show-output tests_format_rules
+#[cfg(test)]
+mod tests_format_rules {
+    use super::*;
+
+    fn header() -> Vec<String> {
+        [
+            "Chave da Nota Fiscal Eletrônica : NF Item (Todos)",
+            "Dia da Emissão : NF Item (Todos)",
+            "Valor Total : NF (Todos) SOMA",
+            "CFOP : NF Item (Todos)",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        // O tipo é separado pelo último ':'
+        let rule: FormatRule = "Valor Total : NF (Todos) SOMA:number".parse().unwrap();
+        assert_eq!(rule.pattern, "Valor Total : NF (Todos) SOMA");
+        assert_eq!(rule.kind, FormatKind::Number);
+
+        assert!("Valor Total".parse::<FormatRule>().is_err());
+        assert!("Valor:float".parse::<FormatRule>().is_err());
+        assert!(":date".parse::<FormatRule>().is_err());
+    }
+
+    #[test]
+    fn test_resolve_with_globs() {
+        let rules: Vec<FormatRule> = [
+            "Chave*:nfe-key",
+            "Dia da Emiss*o : NF*:date",
+            "Valor*:NUMBER",
+        ]
+        .iter()
+        .map(|rule| rule.parse().unwrap())
+        .collect();
+
+        let formats = resolve_formats(&rules, &header()).unwrap();
+        assert_eq!(formats[0], vec![FormatKind::NfeKey]);
+        assert_eq!(formats[1], vec![FormatKind::Date]);
+        assert_eq!(formats[2], vec![FormatKind::Number]);
+        // CFOP não é convertido em número
+        assert!(formats[3].is_empty());
+    }
+
+    #[test]
+    fn test_international_number_rule() {
+        use crate::{analise_line, Arguments, ColumnLayout};
+        use clap::Parser;
+
+        let options = [
+            "unique",
+            "-c",
+            "--format",
+            "Valor*:number",
+            "--number-format",
+            "international",
+        ];
+        let args = Arguments::try_parse_from(options).unwrap();
+
+        let layout = ColumnLayout {
+            formats: resolve_formats(&args.format_rules, &header()).unwrap(),
+            ..ColumnLayout::default()
+        };
+        let line = "35230100000000000000000000000000000000000001;01/05/2023;\"1,234.50\";5102";
+        let processed = analise_line(line, &layout, &args).unwrap();
+        assert_eq!(
+            processed.content,
+            "35230100000000000000000000000000000000000001;01/05/2023;1234.5;5102"
+        );
+
+        // Sem -f ou --format, --number-format não tem efeito
+        assert!(Arguments::try_parse_from(["unique", "-c", "-n", "international"]).is_err());
+    }
+
+    #[test]
+    fn test_unmatched_rule_is_an_error() {
+        let rules: Vec<FormatRule> = vec!["Data*:date".parse().unwrap()];
+        assert!(resolve_formats(&rules, &header()).is_err());
+    }
+}
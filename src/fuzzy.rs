@@ -0,0 +1,228 @@
+use crate::{write_file_or_stderr, UniqueResult};
+use csv::WriterBuilder;
+use rayon::prelude::*;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+/// Par de valores cuja distância de edição está dentro do limite.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyPair {
+    pub left: String,
+    pub right: String,
+    pub distance: usize,
+    pub left_count: usize,
+    pub right_count: usize,
+}
+
+/// Valor distinto de uma coluna e a sua forma normalizada para comparação.
+#[derive(Debug, Clone)]
+struct Candidate {
+    value: String,
+    key: Vec<char>,
+    count: usize,
+}
+
+/// Coleta os valores distintos de uma coluna e encontra os pares próximos
+/// pela distância de Levenshtein.
+///
+/// Para evitar comparar todos contra todos, os valores são agrupados em
+/// blocos pelo primeiro e pelo último caracteres e, dentro de cada bloco,
+/// ordenados pelo comprimento: apenas valores cuja diferença de comprimento
+/// não excede `max_distance` são comparados.
+#[derive(Debug, Default)]
+pub struct FuzzyMatcher {
+    max_distance: usize,
+    values: HashMap<String, (String, usize)>,
+}
+
+impl FuzzyMatcher {
+    pub fn new(max_distance: usize) -> Self {
+        Self {
+            max_distance,
+            values: HashMap::new(),
+        }
+    }
+
+    /// Registra uma ocorrência do valor e a sua chave de comparação.
+    pub fn add(&mut self, value: &str, key: String) {
+        let value = value.trim();
+        if value.is_empty() {
+            return;
+        }
+        match self.values.get_mut(value) {
+            Some((_, count)) => *count += 1,
+            None => {
+                self.values.insert(value.to_string(), (key, 1));
+            }
+        }
+    }
+
+    /// Número de valores distintos coletados.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Encontra os pares de valores distintos com distância até `max_distance`.
+    ///
+    /// Os pares são ordenados pela distância e, em seguida, pelos valores.
+    pub fn pairs(&self) -> Vec<FuzzyPair> {
+        // Blocagem: primeiro e último caracteres da chave normalizada.
+        // Um erro na primeira letra é encontrado pelo bloco da última (e vice-versa).
+        let mut blocks: BTreeMap<(bool, char), Vec<Candidate>> = BTreeMap::new();
+        for (value, (key, count)) in &self.values {
+            let key: Vec<char> = key.trim().chars().collect();
+            if let (Some(&first), Some(&last)) = (key.first(), key.last()) {
+                let candidate = Candidate {
+                    value: value.clone(),
+                    key,
+                    count: *count,
+                };
+                blocks
+                    .entry((false, first))
+                    .or_default()
+                    .push(candidate.clone());
+                blocks.entry((true, last)).or_default().push(candidate);
+            }
+        }
+
+        for block in blocks.values_mut() {
+            block.sort_by(|a, b| a.key.len().cmp(&b.key.len()).then(a.value.cmp(&b.value)));
+        }
+
+        let mut pairs: Vec<FuzzyPair> = blocks
+            .par_iter()
+            .flat_map(|(_, block)| {
+                (0..block.len())
+                    .into_par_iter()
+                    .flat_map_iter(move |i| self.compare_with_next(block, i))
+            })
+            .collect();
+
+        pairs.sort_by(|a, b| {
+            a.distance
+                .cmp(&b.distance)
+                .then_with(|| a.left.cmp(&b.left))
+                .then_with(|| a.right.cmp(&b.right))
+        });
+        // Pares com o mesmo primeiro e último caracteres são encontrados nos dois blocos
+        pairs.dedup_by(|a, b| a.left == b.left && a.right == b.right);
+        pairs
+    }
+
+    /// Compara o candidato `i` com os seguintes do bloco dentro da janela de comprimento.
+    fn compare_with_next(&self, block: &[Candidate], i: usize) -> Vec<FuzzyPair> {
+        let a = &block[i];
+        block[i + 1..]
+            .iter()
+            .take_while(|b| b.key.len() - a.key.len() <= self.max_distance)
+            .filter_map(|b| {
+                let distance = strsim::generic_levenshtein(&a.key, &b.key);
+                (distance <= self.max_distance).then(|| {
+                    let (left, right) = if a.value <= b.value { (a, b) } else { (b, a) };
+                    FuzzyPair {
+                        left: left.value.clone(),
+                        right: right.value.clone(),
+                        distance,
+                        left_count: left.count,
+                        right_count: right.count,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Grava os pares em CSV (separador ';') no arquivo informado ou na saída de erro padrão.
+    pub fn write_report(&self, path: Option<&Path>) -> UniqueResult<()> {
+        let writer = write_file_or_stderr(path)?;
+
+        let mut wtr = WriterBuilder::new().delimiter(b';').from_writer(writer);
+        wtr.write_record(["value_a", "value_b", "distance", "count_a", "count_b"])?;
+
+        for pair in self.pairs() {
+            wtr.write_record([
+                pair.left,
+                pair.right,
+                pair.distance.to_string(),
+                pair.left_count.to_string(),
+                pair.right_count.to_string(),
+            ])?;
+        }
+
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+/// cargo test -- --show-output tests_fuzzy
+#[cfg(test)]
+mod tests_fuzzy {
+    use super::*;
+
+    fn matcher(values: &[&str], max_distance: usize) -> FuzzyMatcher {
+        let mut matcher = FuzzyMatcher::new(max_distance);
+        for value in values {
+            matcher.add(value, value.to_lowercase());
+        }
+        matcher
+    }
+
+    #[test]
+    fn test_pairs_within_distance() {
+        let m = matcher(
+            &[
+                "Comercial Silva LTDA",
+                "Comercial Silva LTDA",
+                "Comercial Silva Ltda.",
+                "Comercial Sliva LTDA",
+                "Transportes Souza",
+            ],
+            2,
+        );
+
+        assert_eq!(m.len(), 4);
+
+        let pairs = m.pairs();
+        for pair in &pairs {
+            println!("{pair:?}");
+        }
+
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[1].right, "Comercial Sliva LTDA");
+        assert_eq!(
+            pairs[0],
+            FuzzyPair {
+                left: "Comercial Silva LTDA".to_string(),
+                right: "Comercial Silva Ltda.".to_string(),
+                distance: 1,
+                left_count: 2,
+                right_count: 1,
+            }
+        );
+        assert!(pairs.iter().all(|p| !p.left.starts_with("Transportes")));
+    }
+
+    #[test]
+    fn test_length_window_and_blocking() {
+        // Comprimentos muito diferentes não são comparados; um erro na
+        // primeira letra é encontrado pelo bloco da última letra
+        let m = matcher(&["Ana", "Anastácia", "Bruno", "Pruno"], 1);
+        let pairs = m.pairs();
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(
+            (pairs[0].left.as_str(), pairs[0].right.as_str()),
+            ("Bruno", "Pruno")
+        );
+    }
+
+    #[test]
+    fn test_empty_values_are_ignored() {
+        let m = matcher(&["", "  "], 3);
+        assert!(m.is_empty());
+    }
+}
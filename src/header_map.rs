@@ -0,0 +1,294 @@
+use crate::{remove_accents, UniqueError, UniqueResult};
+use csv::StringRecord;
+use std::str::FromStr;
+
+/// Nome alternativo de uma coluna (`--header-alias 'ALIAS=CAMPO'`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderAlias {
+    /// Nome encontrado no cabeçalho do arquivo.
+    pub alias: String,
+    /// Nome do campo de `DocsFiscais` correspondente.
+    pub field: String,
+}
+
+impl FromStr for HeaderAlias {
+    type Err = String;
+
+    /// Interpreta "ALIAS=CAMPO". O campo é o texto após o último '='.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (alias, field) = value
+            .rsplit_once('=')
+            .ok_or_else(|| format!("'{value}' must be in the form 'ALIAS=FIELD'"))?;
+
+        let (alias, field) = (alias.trim(), field.trim());
+        if alias.is_empty() || field.is_empty() {
+            return Err(format!("'{value}' has an empty alias or field"));
+        }
+
+        Ok(Self {
+            alias: alias.to_string(),
+            field: field.to_string(),
+        })
+    }
+}
+
+/// Forma normalizada de um nome de coluna, usada na comparação tolerante:
+/// sem acentos, em minúsculas e sem espaços.
+///
+/// Example:
+/// ```
+/// use unique::normalize_header;
+/// assert_eq!(
+///     normalize_header(" Valor Total:NF (Todos)  SOMA "),
+///     normalize_header("valor total : NF (todos) soma"),
+/// );
+/// assert_eq!(normalize_header("Observações"), "observacoes");
+/// ```
+pub fn normalize_header(name: &str) -> String {
+    remove_accents(name)
+        .to_lowercase()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect()
+}
+
+/// Resultado da associação do cabeçalho aos campos conhecidos.
+#[derive(Debug, Clone)]
+pub struct HeaderMapping {
+    /// Cabeçalho com os nomes dos campos no lugar dos nomes encontrados.
+    pub header: StringRecord,
+    /// Colunas renomeadas: (nome no arquivo, nome do campo).
+    pub renamed: Vec<(String, String)>,
+    /// Colunas do arquivo que não correspondem a nenhum campo.
+    pub unmatched_headers: Vec<String>,
+    /// Campos sem coluna correspondente no arquivo (ficam com o valor padrão).
+    pub missing_fields: Vec<String>,
+}
+
+/// Associa as colunas do cabeçalho aos campos conhecidos (`fields`).
+///
+/// Cada coluna é procurada pelo nome exato, em seguida pelos aliases e,
+/// por fim, pela forma normalizada (ver `normalize_header`). Um campo é
+/// associado a uma única coluna: a primeira encontrada.
+///
+/// Um alias que aponta para um campo inexistente é um erro.
+///
+/// Example:
+/// ```
+/// use csv::StringRecord;
+/// use unique::{map_headers, HeaderAlias};
+/// let fields = vec!["CNPJ".to_string(), "Valor Total".to_string(), "Emissão".to_string()];
+/// let aliases: Vec<HeaderAlias> = vec!["Data=Emissão".parse().unwrap()];
+/// let header = StringRecord::from(vec!["cnpj", "VALOR  TOTAL", "Data", "Extra"]);
+/// let mapping = map_headers(&header, &fields, &aliases).unwrap();
+/// assert_eq!(mapping.header, StringRecord::from(vec!["CNPJ", "Valor Total", "Emissão", "Extra"]));
+/// assert_eq!(mapping.unmatched_headers, vec!["Extra"]);
+/// assert!(mapping.missing_fields.is_empty());
+/// ```
+pub fn map_headers(
+    header: &StringRecord,
+    fields: &[String],
+    aliases: &[HeaderAlias],
+) -> UniqueResult<HeaderMapping> {
+    for alias in aliases {
+        if !fields.contains(&alias.field) {
+            return Err(UniqueError::ColumnNotFound(alias.field.clone()));
+        }
+    }
+
+    let normalized_fields: Vec<String> = fields.iter().map(|f| normalize_header(f)).collect();
+    let mut used = vec![false; fields.len()];
+
+    let mut names: Vec<String> = Vec::with_capacity(header.len());
+    let mut renamed = Vec::new();
+    let mut unmatched_headers = Vec::new();
+
+    // Primeiro os nomes exatos, para que não sejam tomados por outra coluna
+    let exact: Vec<Option<usize>> = header
+        .iter()
+        .map(|name| {
+            let index = fields.iter().position(|f| f == name)?;
+            (!std::mem::replace(&mut used[index], true)).then_some(index)
+        })
+        .collect();
+
+    for (name, exact) in header.iter().zip(exact) {
+        if exact.is_some() {
+            names.push(name.to_string());
+            continue;
+        }
+
+        let normalized = normalize_header(name);
+        let index = aliases
+            .iter()
+            .filter(|a| normalize_header(&a.alias) == normalized)
+            .find_map(|a| fields.iter().position(|f| *f == a.field))
+            .filter(|&i| !used[i])
+            .or_else(|| {
+                normalized_fields
+                    .iter()
+                    .enumerate()
+                    .position(|(i, f)| !used[i] && *f == normalized)
+            });
+
+        match index {
+            Some(i) => {
+                used[i] = true;
+                renamed.push((name.to_string(), fields[i].clone()));
+                names.push(fields[i].clone());
+            }
+            None => {
+                unmatched_headers.push(name.to_string());
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    let missing_fields = fields
+        .iter()
+        .zip(&used)
+        .filter(|(_, &used)| !used)
+        .map(|(field, _)| field.clone())
+        .collect();
+
+    Ok(HeaderMapping {
+        header: StringRecord::from(names),
+        renamed,
+        unmatched_headers,
+        missing_fields,
+    })
+}
+
+/// Similaridade mínima (0 a 1) para sugerir uma coluna parecida.
+const MIN_SIMILARITY: f64 = 0.7;
+
+/// Verifica se as colunas obrigatórias existem no cabeçalho.
+///
+/// A comparação usa a forma normalizada (ver `normalize_header`). O erro
+/// lista todas as colunas ausentes e, para cada uma, a coluna mais
+/// parecida do cabeçalho, se houver.
+///
+/// Example:
+/// ```
+/// use csv::StringRecord;
+/// use unique::check_required_columns;
+/// let header = StringRecord::from(vec!["CNPJ", "Chave NFe", "Valor"]);
+/// let required = vec!["cnpj".to_string(), "Chave da NFe".to_string()];
+/// let error = check_required_columns(&header, &required).unwrap_err();
+/// assert!(error.to_string().contains("'Chave da NFe' (similar: 'Chave NFe')"));
+/// ```
+pub fn check_required_columns(header: &StringRecord, required: &[String]) -> UniqueResult<()> {
+    let normalized_header: Vec<String> = header.iter().map(normalize_header).collect();
+
+    let missing: Vec<String> = required
+        .iter()
+        .filter(|name| !normalized_header.contains(&normalize_header(name)))
+        .map(|name| {
+            let normalized = normalize_header(name);
+            let close_match = normalized_header
+                .iter()
+                .zip(header.iter())
+                .map(|(h, original)| (strsim::normalized_levenshtein(h, &normalized), original))
+                .filter(|(similarity, _)| *similarity >= MIN_SIMILARITY)
+                .max_by(|a, b| a.0.total_cmp(&b.0));
+
+            match close_match {
+                Some((_, similar)) => format!("\n - '{name}' (similar: '{similar}')"),
+                None => format!("\n - '{name}'"),
+            }
+        })
+        .collect();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(UniqueError::MissingColumns(missing.concat()))
+    }
+}
+
+/// cargo test -- --show-output tests_header_map
+#[cfg(test)]
+mod tests_header_map {
+    use super::*;
+
+    fn fields() -> Vec<String> {
+        [
+            "CNPJ do Contribuinte : NF Item (Todos)",
+            "Valor Total : NF (Todos) SOMA",
+            "Observações : NF (Todos)",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn test_tolerant_matching() {
+        let header = StringRecord::from(vec![
+            "cnpj do contribuinte: NF Item (Todos)",
+            "Valor Total :  NF (Todos) SOMA",
+            "Observacoes : NF (TODOS)",
+        ]);
+        let mapping = map_headers(&header, &fields(), &[]).unwrap();
+        assert_eq!(mapping.header, StringRecord::from(fields()));
+        assert_eq!(mapping.renamed.len(), 3);
+        assert!(mapping.unmatched_headers.is_empty());
+        assert!(mapping.missing_fields.is_empty());
+    }
+
+    #[test]
+    fn test_aliases_and_warnings() {
+        let aliases: Vec<HeaderAlias> = vec!["Valor da Nota=Valor Total : NF (Todos) SOMA"
+            .parse()
+            .unwrap()];
+        let header = StringRecord::from(vec![
+            "CNPJ do Contribuinte : NF Item (Todos)",
+            "VALOR DA NOTA",
+            "Coluna Nova",
+            "cnpj do contribuinte : nf item (todos)",
+        ]);
+        let mapping = map_headers(&header, &fields(), &aliases).unwrap();
+        assert_eq!(&mapping.header[1], "Valor Total : NF (Todos) SOMA");
+        // O campo já associado pelo nome exato não é tomado pela cópia
+        assert_eq!(
+            mapping.unmatched_headers,
+            vec!["Coluna Nova", "cnpj do contribuinte : nf item (todos)"]
+        );
+        assert_eq!(mapping.missing_fields, vec!["Observações : NF (Todos)"]);
+    }
+
+    #[test]
+    fn test_required_columns() {
+        let header = StringRecord::from(vec![
+            "CNPJ do Contribuinte : NF Item (Todos)",
+            "Chave da Nota Fiscal : NF Item (Todos)",
+        ]);
+        let required = vec![
+            "cnpj do contribuinte : nf item (todos)".to_string(),
+            "Chave da Nota Fiscal Eletrônica : NF Item (Todos)".to_string(),
+            "Dia da Emissão : NF Item (Todos)".to_string(),
+        ];
+        match check_required_columns(&header, &required) {
+            Err(UniqueError::MissingColumns(message)) => assert_eq!(
+                message,
+                "\n - 'Chave da Nota Fiscal Eletrônica : NF Item (Todos)' \
+                 (similar: 'Chave da Nota Fiscal : NF Item (Todos)')\
+                 \n - 'Dia da Emissão : NF Item (Todos)'"
+            ),
+            other => panic!("unexpected result: {other:?}"),
+        }
+
+        assert!(check_required_columns(&header, &required[..1]).is_ok());
+        assert!(check_required_columns(&header, &[]).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_alias() {
+        assert!("Valor".parse::<HeaderAlias>().is_err());
+        assert!("=Valor".parse::<HeaderAlias>().is_err());
+
+        let aliases: Vec<HeaderAlias> = vec!["Valor=Campo Inexistente".parse().unwrap()];
+        let header = StringRecord::from(vec!["Valor"]);
+        assert!(map_headers(&header, &fields(), &aliases).is_err());
+    }
+}
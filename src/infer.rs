@@ -0,0 +1,400 @@
+use crate::{
+    null_values, ColumnSpec, ColumnType, NumberFormat, Schema, UniqueResult, DATA_FORMAT,
+    NULL_VALUES,
+};
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, StringRecord};
+use regex::Regex;
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt,
+    io::Write,
+    sync::LazyLock as Lazy,
+};
+
+/// Limite de valores distintos guardados por coluna (cardinalidade).
+const MAX_DISTINCT: usize = 10_000;
+
+/// Formato ISO das datas (AAAA-MM-DD).
+const ISO_DATE_FORMAT: &str = "%Y-%m-%d";
+
+// Candidatos de tipo, combinados em uma máscara de bits.
+const NFE_KEY: u8 = 1 << 0;
+const CNPJ_CPF: u8 = 1 << 1;
+const INTEGER: u8 = 1 << 2;
+const DATE_BR: u8 = 1 << 3;
+const DATE_ISO: u8 = 1 << 4;
+const DECIMAL_BR: u8 = 1 << 5;
+const DECIMAL_INTL: u8 = 1 << 6;
+const ALL_KINDS: u8 = u8::MAX;
+
+/// Tipo inferido para os valores de uma coluna.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InferredKind {
+    /// Chave de NF-e (44 dígitos).
+    NfeKey,
+    /// CNPJ ou CPF, com ou sem pontuação.
+    CnpjCpf,
+    Integer,
+    /// Número decimal no formato brasileiro (1.234,56).
+    DecimalBrazilian,
+    /// Número decimal no formato internacional (1,234.56).
+    DecimalInternational,
+    /// Data no formato informado.
+    Date(&'static str),
+    Text,
+}
+
+impl InferredKind {
+    /// Tipo mais específico compatível com todos os valores, em ordem de prioridade.
+    fn from_candidates(candidates: u8) -> Self {
+        [
+            (NFE_KEY, InferredKind::NfeKey),
+            (CNPJ_CPF, InferredKind::CnpjCpf),
+            (INTEGER, InferredKind::Integer),
+            (DATE_BR, InferredKind::Date(DATA_FORMAT)),
+            (DATE_ISO, InferredKind::Date(ISO_DATE_FORMAT)),
+            (DECIMAL_BR, InferredKind::DecimalBrazilian),
+            (DECIMAL_INTL, InferredKind::DecimalInternational),
+        ]
+        .into_iter()
+        .find(|(flag, _)| candidates & flag != 0)
+        .map_or(InferredKind::Text, |(_, kind)| kind)
+    }
+}
+
+impl fmt::Display for InferredKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InferredKind::NfeKey => write!(f, "nfe-key"),
+            InferredKind::CnpjCpf => write!(f, "cnpj/cpf"),
+            InferredKind::Integer => write!(f, "integer"),
+            InferredKind::DecimalBrazilian => write!(f, "decimal (brazilian)"),
+            InferredKind::DecimalInternational => write!(f, "decimal (international)"),
+            InferredKind::Date(format) => write!(f, "date ({format})"),
+            InferredKind::Text => write!(f, "text"),
+        }
+    }
+}
+
+/// Tipos compatíveis com um valor (máscara de bits).
+fn candidates(value: &str) -> u8 {
+    static NFE_KEY_REGEX: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^\d{44}$").expect("NFE_KEY_REGEX inválida!"));
+    static CNPJ_CPF_REGEX: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^(\d{11}|\d{14}|\d{3}\.\d{3}\.\d{3}-\d{2}|\d{2}\.\d{3}\.\d{3}/\d{4}-\d{2})$")
+            .expect("CNPJ_CPF_REGEX inválida!")
+    });
+    static INTEGER_REGEX: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^-?(0|[1-9]\d*)$").expect("INTEGER_REGEX inválida!"));
+    static DECIMAL_BR_REGEX: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^-?(\d{1,3}(\.\d{3})+|\d+)(,\d+)?$").expect("DECIMAL_BR_REGEX inválida!")
+    });
+    static DECIMAL_INTL_REGEX: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^-?(\d{1,3}(,\d{3})+|\d+)(\.\d+)?$").expect("DECIMAL_INTL_REGEX inválida!")
+    });
+
+    let mut kinds = 0;
+    let mut set = |flag: u8, matches: bool| {
+        if matches {
+            kinds |= flag;
+        }
+    };
+
+    set(NFE_KEY, NFE_KEY_REGEX.is_match(value));
+    set(CNPJ_CPF, CNPJ_CPF_REGEX.is_match(value));
+    set(
+        DATE_BR,
+        NaiveDate::parse_from_str(value, DATA_FORMAT).is_ok(),
+    );
+    set(
+        DATE_ISO,
+        NaiveDate::parse_from_str(value, ISO_DATE_FORMAT).is_ok(),
+    );
+
+    // Códigos com zeros à esquerda ("0102") não são números
+    let digits = value.trim_start_matches('-');
+    let leading_zero = digits.len() > 1
+        && digits.starts_with('0')
+        && digits[1..].starts_with(|c: char| c.is_ascii_digit());
+
+    if !leading_zero {
+        set(INTEGER, INTEGER_REGEX.is_match(value));
+        set(DECIMAL_BR, DECIMAL_BR_REGEX.is_match(value));
+        set(DECIMAL_INTL, DECIMAL_INTL_REGEX.is_match(value));
+    }
+
+    kinds
+}
+
+/// Perfil dos valores de uma coluna.
+#[derive(Debug, Clone)]
+pub struct ColumnProfile {
+    pub name: String,
+    /// Valores não nulos.
+    pub non_null: usize,
+    pub nulls: usize,
+    /// Marcadores de nulo encontrados (ver `null_values`).
+    pub null_markers: BTreeSet<String>,
+    /// Valores distintos (até `MAX_DISTINCT`).
+    distinct: HashSet<String>,
+    /// Comprimento máximo (em caracteres).
+    pub max_length: usize,
+    candidates: u8,
+}
+
+impl ColumnProfile {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.trim().to_string(),
+            non_null: 0,
+            nulls: 0,
+            null_markers: BTreeSet::new(),
+            distinct: HashSet::new(),
+            max_length: 0,
+            candidates: ALL_KINDS,
+        }
+    }
+
+    /// Registra um valor da coluna.
+    pub fn add(&mut self, value: &str) {
+        let value = value.trim();
+
+        if let Some(marker) = null_values()
+            .iter()
+            .find(|m| m.trim().eq_ignore_ascii_case(value))
+        {
+            self.nulls += 1;
+            self.null_markers.insert(marker.to_string());
+            return;
+        }
+
+        self.non_null += 1;
+        self.max_length = self.max_length.max(value.chars().count());
+        self.candidates &= candidates(value);
+        if self.distinct.len() < MAX_DISTINCT {
+            self.distinct.insert(value.to_string());
+        }
+    }
+
+    /// Tipo inferido (texto, se a coluna só tiver valores nulos).
+    pub fn kind(&self) -> InferredKind {
+        if self.non_null == 0 {
+            InferredKind::Text
+        } else {
+            InferredKind::from_candidates(self.candidates)
+        }
+    }
+
+    /// Número de valores distintos e se o limite foi atingido.
+    pub fn cardinality(&self) -> (usize, bool) {
+        (self.distinct.len(), self.distinct.len() >= MAX_DISTINCT)
+    }
+
+    /// Descrição da coluna no esquema.
+    fn column_spec(&self) -> ColumnSpec {
+        let (column_type, number_format, date_format) = match self.kind() {
+            InferredKind::Integer => (ColumnType::Integer, None, None),
+            InferredKind::DecimalBrazilian => (ColumnType::Float, None, None),
+            InferredKind::DecimalInternational => {
+                (ColumnType::Float, Some(NumberFormat::International), None)
+            }
+            InferredKind::Date(format) => (ColumnType::Date, None, Some(format.to_string())),
+            _ => (ColumnType::String, None, None),
+        };
+
+        // Marcadores encontrados na coluna; o vazio continua nulo, se estiver na lista
+        let null_markers = (!self.null_markers.is_empty()).then(|| {
+            let mut markers = self.null_markers.clone();
+            if null_values().iter().any(|m| m.trim().is_empty()) {
+                markers.insert(String::new());
+            }
+            markers.into_iter().collect()
+        });
+
+        ColumnSpec {
+            name: self.name.clone(),
+            aliases: Vec::new(),
+            column_type,
+            null_values: null_markers,
+            date_format,
+            number_format,
+            required: false,
+        }
+    }
+}
+
+/// Inferência do esquema a partir das linhas de um arquivo CSV.
+#[derive(Debug, Clone)]
+pub struct SchemaInference {
+    separator: char,
+    pub rows: usize,
+    pub columns: Vec<ColumnProfile>,
+}
+
+impl SchemaInference {
+    /// Inicia a inferência com o cabeçalho do arquivo.
+    pub fn new(header: &str, separator: char) -> UniqueResult<Self> {
+        let columns = split_record(header, separator)?
+            .iter()
+            .map(ColumnProfile::new)
+            .collect();
+
+        Ok(Self {
+            separator,
+            rows: 0,
+            columns,
+        })
+    }
+
+    /// Registra uma linha de dados. Colunas excedentes são ignoradas.
+    pub fn add_line(&mut self, line: &str) -> UniqueResult<()> {
+        let record = split_record(line, self.separator)?;
+        for (profile, value) in self.columns.iter_mut().zip(record.iter()) {
+            profile.add(value);
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Esquema compatível com `--schema`.
+    ///
+    /// Com `--null-values`, a lista de nulos usada na inferência é a do esquema.
+    pub fn schema(&self) -> Schema {
+        let custom_nulls = null_values().iter().ne(NULL_VALUES.iter());
+
+        Schema {
+            null_values: custom_nulls.then(|| null_values().to_vec()),
+            number_format: NumberFormat::Brazilian,
+            columns: self
+                .columns
+                .iter()
+                .map(ColumnProfile::column_spec)
+                .collect(),
+        }
+    }
+
+    /// Resumo legível da inferência, coluna por coluna.
+    pub fn write_summary<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        writeln!(w, "\n📋 SCHEMA INFERENCE")?;
+        writeln!(w, "   • Rows scanned: {}", self.rows)?;
+        writeln!(
+            w,
+            "   • CSV column separator: '{}'",
+            self.separator.escape_default()
+        )?;
+
+        for (index, profile) in self.columns.iter().enumerate() {
+            let (distinct, capped) = profile.cardinality();
+            let plus = if capped { "+" } else { "" };
+
+            write!(
+                w,
+                "   {:>3}. {}: {} | distinct: {distinct}{plus} | max length: {}",
+                index + 1,
+                profile.name,
+                profile.kind(),
+                profile.max_length
+            )?;
+            if profile.nulls > 0 {
+                let markers: Vec<String> = profile
+                    .null_markers
+                    .iter()
+                    .map(|m| format!("'{m}'"))
+                    .collect();
+                write!(w, " | nulls: {} ({})", profile.nulls, markers.join(", "))?;
+            }
+            writeln!(w)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Separa uma linha CSV em seus campos.
+fn split_record(line: &str, separator: char) -> UniqueResult<StringRecord> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(separator as u8)
+        .has_headers(false)
+        .from_reader(line.as_bytes());
+
+    match reader.records().next() {
+        Some(result) => Ok(result?),
+        None => Ok(StringRecord::new()),
+    }
+}
+
+/// cargo test -- --show-output tests_infer
+#[cfg(test)]
+mod tests_infer {
+    use super::*;
+
+    fn infer(lines: &[&str]) -> SchemaInference {
+        let mut inference = SchemaInference::new(lines[0], ';').unwrap();
+        for line in &lines[1..] {
+            inference.add_line(line).unwrap();
+        }
+        inference
+    }
+
+    #[test]
+    fn test_infer_kinds() {
+        let inference = infer(&[
+            "Chave;CNPJ;CFOP;Código;Valor;Amount;Data;Nome",
+            "35230100000000000000000000000000000000000001;12.345.678/0001-99;5102;0102;1.234,56;1,234.56;31/01/2024;Ana",
+            "35230100000000000000000000000000000000000002;12345678000199;6102;0405;10,5;10.5;01/02/2024;Bia",
+            "35230100000000000000000000000000000000000003;123.456.789-01;5102;0999;7;7;N/A;",
+        ]);
+
+        let kinds: Vec<InferredKind> = inference.columns.iter().map(|c| c.kind()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                InferredKind::NfeKey,
+                InferredKind::CnpjCpf,
+                InferredKind::Integer,
+                InferredKind::Text,
+                InferredKind::DecimalBrazilian,
+                InferredKind::DecimalInternational,
+                InferredKind::Date("%d/%m/%Y"),
+                InferredKind::Text,
+            ]
+        );
+
+        let cfop = &inference.columns[2];
+        assert_eq!(cfop.cardinality(), (2, false));
+        assert_eq!(cfop.max_length, 4);
+
+        let data = &inference.columns[6];
+        assert_eq!(data.nulls, 1);
+        assert!(data.null_markers.contains("N/A"));
+    }
+
+    #[test]
+    fn test_null_markers_in_schema() {
+        let inference = infer(&["Data;Nome", "31/01/2024;Ana", "N/A;Bia", "NULO;"]);
+        let schema = inference.schema();
+
+        // Os marcadores encontrados (e o vazio) são os nulos da coluna
+        let markers = ["", "N/A", "NULO"].map(String::from).to_vec();
+        assert_eq!(schema.columns[0].column_type, ColumnType::Date);
+        assert_eq!(schema.columns[0].null_values, Some(markers));
+        assert_eq!(schema.columns[1].null_values, Some(vec![String::new()]));
+    }
+
+    #[test]
+    fn test_inferred_schema_is_loadable() {
+        let inference = infer(&["Valor;Amount;Dia", "1,5;1.5;2024-01-31"]);
+        let text = toml::to_string(&inference.schema()).unwrap();
+        println!("{text}");
+
+        let schema = Schema::parse(&text).unwrap();
+        assert_eq!(schema.columns.len(), 3);
+        assert_eq!(schema.columns[0].column_type, ColumnType::Float);
+        assert_eq!(
+            schema.columns[1].number_format,
+            Some(NumberFormat::International)
+        );
+        assert_eq!(schema.columns[2].date_format.as_deref(), Some("%Y-%m-%d"));
+    }
+}
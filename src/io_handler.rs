@@ -2,8 +2,8 @@ use encoding_rs::WINDOWS_1252;
 use encoding_rs_io::DecodeReaderBytesBuilder;
 use std::{
     fs,
-    io::{self, BufRead, BufReader, Read},
-    path::PathBuf,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
 };
 
 use crate::{UniqueError, UniqueResult};
@@ -34,6 +34,22 @@ pub fn read_file_or_stdin(path: &Option<PathBuf>) -> UniqueResult<Box<dyn BufRea
     }
 }
 
+/// Cria (ou trunca) o arquivo de saída, informando o caminho em caso de erro.
+pub fn create_file(path: &Path) -> UniqueResult<fs::File> {
+    fs::File::create(path).map_err(|e| UniqueError::FileError {
+        path: path.display().to_string(),
+        source: e,
+    })
+}
+
+/// Abre o arquivo de saída ou Stderr (se nenhum caminho for informado).
+pub fn write_file_or_stderr(path: Option<&Path>) -> UniqueResult<Box<dyn Write>> {
+    match path {
+        Some(path) => Ok(Box::new(create_file(path)?)),
+        None => Ok(Box::new(io::stderr())),
+    }
+}
+
 /// Converte bytes em String, tentando UTF-8 e Windows-1252.
 /// Propaga os erros em caso de falha total, sem dar panic.
 pub fn get_string_utf8_from_slice_bytes(slice_bytes: &[u8]) -> UniqueResult<String> {
@@ -1,20 +1,52 @@
+mod aggregate;
 mod args;
+mod columns;
+mod comparison;
 mod docs_fiscais;
 mod error;
+mod format_rules;
+mod fuzzy;
+mod header_map;
+mod infer;
 mod io_handler;
 mod models;
+mod near_duplicates;
+mod parquet_output;
 mod processor;
-
+mod report;
+mod schema;
+mod separator;
+mod spreadsheet;
+mod stats;
+mod xlsx_output;
+
+pub use aggregate::*;
 pub use args::*;
+pub use columns::*;
+pub use comparison::*;
 pub use docs_fiscais::*;
-pub use error::{UniqueError, UniqueResult};
+pub use error::{LineError, UniqueError, UniqueResult};
+pub use format_rules::*;
+pub use fuzzy::*;
+pub use header_map::*;
+pub use infer::*;
 pub use io_handler::*;
 pub use models::*;
+pub use near_duplicates::*;
+pub use parquet_output::*;
 pub use processor::*;
+pub use report::*;
+pub use schema::*;
+pub use separator::*;
+pub use spreadsheet::*;
+pub use stats::*;
+pub use xlsx_output::*;
 
 use chrono::NaiveDate;
 use regex::Regex;
+use rust_decimal::Decimal;
 use std::ops::Deref;
+use std::str::FromStr;
 use std::sync::LazyLock as Lazy;
 
 pub use claudiofsr_lib::StrExtension;
@@ -161,7 +193,8 @@ where
 }
 
 /// Formata strings numéricas para o padrão computational (f64)
-/// com base no formato de entrada (Brasileiro ou Internacional).
+/// com base no formato de entrada (Brasileiro ou Internacional),
+/// sem perder os dígitos que não cabem em um f64.
 pub fn format_number<T: AsRef<str>>(text: T, format: NumberFormat) -> String {
     let original = text.as_ref().trim();
 
@@ -175,6 +208,34 @@ pub fn format_number<T: AsRef<str>>(text: T, format: NumberFormat) -> String {
         NumberFormat::International => '.',
     };
 
+    let cleaned = clean_number(original, format);
+
+    // Decimal preserva todos os dígitos; f64 apenas para o que Decimal não lê (ex.: "1e5")
+    let number = match Decimal::from_str(&cleaned) {
+        Ok(num) => Ok(num.normalize().to_string()),
+        Err(_) => cleaned.parse::<f64>().map(|num| {
+            // remoção de sinal negativo de Zero: "-0.0" --> "0.0"
+            let num = if num == 0.0 { 0.0 } else { num };
+            num.to_string()
+        }),
+    };
+
+    match number {
+        Ok(s) => {
+            // REGRA: Só força ".0" se o separador DECIMAL original estava presente
+            // e o resultado do to_string() não possui ponto decimal.
+            if original.contains(decimal_sep) && !s.contains('.') {
+                format!("{}.0", s)
+            } else {
+                s
+            }
+        }
+        Err(_) => original.to_string(),
+    }
+}
+
+/// Remove o separador de milhar e troca o separador decimal por ponto.
+fn clean_number(original: &str, format: NumberFormat) -> String {
     let mut cleaned = String::with_capacity(original.len());
 
     match format {
@@ -197,22 +258,56 @@ pub fn format_number<T: AsRef<str>>(text: T, format: NumberFormat) -> String {
         }
     }
 
-    match cleaned.parse::<f64>() {
-        Ok(num) => {
-            // remoção de sinal negativo de Zero: "-0.0" --> "0.0"
-            let num = if num == 0.0 { 0.0 } else { num };
-            let s = num.to_string();
+    cleaned
+}
 
-            // REGRA: Só força ".0" se o separador DECIMAL original estava presente
-            // e o resultado do to_string() não possui ponto decimal.
-            if original.contains(decimal_sep) && !s.contains('.') {
-                format!("{}.0", s)
-            } else {
-                s
-            }
-        }
-        Err(_) => original.to_string(),
+/// Converte strings numéricas em decimais exatos (sem passar por f64),
+/// com base no formato de entrada (Brasileiro ou Internacional).
+///
+/// Números cujos separadores não correspondem ao formato são rejeitados
+/// em vez de lidos de outra forma (ver `valid_separators`).
+///
+/// Example:
+/// ```
+/// use unique::{parse_decimal, NumberFormat};
+/// let value = parse_decimal(" 1.250,10 ", NumberFormat::Brazilian).unwrap();
+/// assert_eq!(value.to_string(), "1250.10");
+/// let value = parse_decimal("1,250.10", NumberFormat::International).unwrap();
+/// assert_eq!(value.to_string(), "1250.10");
+/// assert!(parse_decimal("abc", NumberFormat::Brazilian).is_none());
+/// // Formato internacional lido como brasileiro
+/// assert!(parse_decimal("1,234.50", NumberFormat::Brazilian).is_none());
+/// assert!(parse_decimal("10.25", NumberFormat::Brazilian).is_none());
+/// ```
+pub fn parse_decimal<T: AsRef<str>>(text: T, format: NumberFormat) -> Option<Decimal> {
+    let original = text.as_ref().trim();
+    if !valid_separators(original, format) {
+        return None;
+    }
+    Decimal::from_str(&clean_number(original, format)).ok()
+}
+
+/// Indica se os separadores estão nas posições do formato: no máximo um
+/// separador decimal e, na parte inteira, separadores de milhar apenas
+/// entre grupos de três dígitos.
+fn valid_separators(number: &str, format: NumberFormat) -> bool {
+    let (thousands, decimal) = match format {
+        NumberFormat::Brazilian => ('.', ','),
+        NumberFormat::International => (',', '.'),
+    };
+
+    let number = number.trim_start_matches(['-', '+']);
+    let (integer, fraction) = number.split_once(decimal).unwrap_or((number, ""));
+    if fraction.contains([thousands, decimal]) {
+        return false;
+    }
+    if !integer.contains(thousands) {
+        return true;
     }
+
+    let mut groups = integer.split(thousands);
+    let first = groups.next().unwrap_or_default();
+    (1..=3).contains(&first.len()) && groups.all(|group| group.len() == 3)
 }
 
 /*
@@ -311,6 +406,22 @@ pub fn check_nfe(nfe: &str) -> bool {
     digito_verificador == nums[43] && cnpj_valido
 }
 
+/// Conta as chaves de NF-e (sequências de 44 dígitos) com dígito verificador inválido.
+///
+/// Example:
+/// ```
+/// use unique::count_invalid_nfe_keys;
+/// let line = "29211212345678000195550010000000111000474894;29211212345678000195550010000000111000474895";
+/// assert_eq!(count_invalid_nfe_keys(line), 1);
+/// ```
+pub fn count_invalid_nfe_keys(line: &str) -> usize {
+    split_line_on_numbers(line)
+        .iter()
+        .filter(|part| part.contains_only_digits() && part.contains_num_digits(44))
+        .filter(|nfe| !check_nfe(nfe))
+        .count()
+}
+
 #[cfg(test)]
 mod functions {
     use super::*;
@@ -518,6 +629,12 @@ mod test_format_number {
         assert_eq!(format_number("7,60", br), "7.6");
         assert_eq!(format_number("1,6500", br), "1.65");
         assert_eq!(format_number("7,6000", br), "7.6");
+
+        // Dígitos além da precisão do f64 são mantidos
+        assert_eq!(
+            format_number("12.345.678.901.234.567,89", br),
+            "12345678901234567.89"
+        );
     }
 
     #[test]
@@ -1,11 +1,10 @@
-use clap::Parser;
 use csv::StringRecord;
-use execution_time::ExecutionTime;
 use rayon::prelude::*;
 
 use std::{
-    collections::HashSet,
-    io::BufRead,
+    borrow::Cow,
+    collections::{hash_map::Entry, HashMap, HashSet},
+    io::{BufRead, BufWriter, Cursor, Read, Write},
     process,
     sync::atomic::{AtomicUsize, Ordering},
 };
@@ -15,6 +14,7 @@ use unique::*;
 
 const CHUNK_SIZE: usize = 10_000;
 const NEWLINE_BYTE: u8 = b'\n';
+const SAMPLE_LINES: usize = 100; // Lines sampled by --separator auto
 
 /*
 Inspiração: uniq, huniq e semiuniq.
@@ -31,29 +31,101 @@ unique arquivo.csv --csv -f --number-format international (Padrão: 1,234.56 ->
 */
 
 fn main() -> UniqueResult<()> {
-    if let Err(error) = run() {
-        eprintln!("Operation failed!\n{}", error);
-        process::exit(1); // Explicitly exit with failure code
+    match run() {
+        Ok(0) => Ok(()),
+        Ok(code) => process::exit(code), // Validation result (--check)
+        Err(error) => {
+            eprintln!("Operation failed!\n{}", error);
+            process::exit(1); // Explicitly exit with failure code
+        }
     }
-    Ok(())
 }
 
 /// Main orchestration logic for the unique line processor.
 ///
 /// This function coordinates the reading, parallel processing,
 /// and sequential deduplication of lines.
-fn run() -> UniqueResult<()> {
+///
+/// Returns the process exit code (see `--check`).
+fn run() -> UniqueResult<i32> {
     let timer = execution_time::ExecutionTime::start();
-    let arguments: Arguments = Arguments::parse();
+    let mut arguments: Arguments = Arguments::parse_args();
+
+    if let Some(Command::InferSchema(infer_args)) = &arguments.command {
+        infer_schema(infer_args)?;
+        return Ok(0);
+    }
+
+    // Values treated as null when reading (--null-values)
+    configure_null_values(&arguments.null_values, arguments.replace_null_values);
 
     // Initialize input buffer (File or Stdin)
-    let mut buffer: Box<dyn BufRead> = read_file_or_stdin(&arguments.file)?;
+    let mut buffer: Box<dyn BufRead> = match &arguments.file {
+        // Spreadsheets are converted to CSV text and parsed as CSV files
+        Some(path) if is_spreadsheet(path) => {
+            if arguments.separator == Separator::Auto {
+                arguments.separator = Separator::Char(DEFAULT_SEPARATOR);
+            }
+            let csv = read_spreadsheet(
+                path,
+                arguments.sheet.as_deref(),
+                arguments.separator.char(),
+                arguments.number_format,
+            )?;
+            Box::new(Cursor::new(csv))
+        }
+        _ if arguments.sheet.is_some() => {
+            return Err(UniqueError::Spreadsheet {
+                path: arguments
+                    .file
+                    .as_ref()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default(),
+                message:
+                    "--sheet requer um arquivo de planilha (.xlsx, .xlsm, .xlsb, .xls ou .ods)"
+                        .to_string(),
+            });
+        }
+        _ => read_file_or_stdin(&arguments.file)?,
+    };
+
+    // Detect the CSV separator from a sample (--separator auto)
+    let separator_detected = arguments.parse_csv_file && arguments.separator == Separator::Auto;
+    if separator_detected {
+        let mut sample: Vec<u8> = Vec::new();
+        for _ in 0..SAMPLE_LINES {
+            if buffer.read_until(NEWLINE_BYTE, &mut sample)? == 0 {
+                break;
+            }
+        }
+        let separator = detect_separator(&sample).unwrap_or(DEFAULT_SEPARATOR);
+        arguments.separator = Separator::Char(separator);
+
+        // The sampled lines are processed normally
+        buffer = Box::new(Cursor::new(sample).chain(buffer));
+    }
 
     // Shared state for tracking uniqueness and consistency
     let mut delimiter_set: HashSet<usize> = HashSet::new();
-    let mut uniq_hashes: HashSet<String> = HashSet::new();
+    let mut uniq_hashes: HashMap<String, usize> = HashMap::new(); // hash => first line number
+    let mut num_unique_lines: usize = 0;
     let mut num_repeated_lines: usize = 0;
     let atomic_empty_lines = AtomicUsize::new(0);
+    let atomic_invalid_keys = AtomicUsize::new(0);
+
+    // In check mode, the data is validated but not printed
+    let print_data = !arguments.check;
+
+    // Line numbers of every key (only with --report-duplicates)
+    let mut duplicate_report: Option<DuplicateReport> = arguments
+        .report_duplicates
+        .as_ref()
+        .map(|_| DuplicateReport::new());
+
+    // Near-duplicate clusters (SimHash + LSH)
+    let mut near_index: Option<NearDuplicateIndex> = arguments
+        .near_duplicates
+        .map(|threshold| NearDuplicateIndex::new(threshold, arguments.cluster_report.is_some()));
 
     // CSV Header management
     let mut header_record: Option<StringRecord> = None;
@@ -81,11 +153,7 @@ fn run() -> UniqueResult<()> {
             // Header Found: Parse into StringRecord for Serde context
             line_number += 1;
 
-            let h_record = StringRecord::from_iter(header_string.split(arguments.separator));
-
-            if !arguments.only_print_repeated_lines {
-                println!("{}", header_string);
-            }
+            let h_record = parse_record(&header_string, &arguments)?;
 
             delimiter_set.insert(h_record.len());
             header_record = Some(h_record); // Persist header context for the processing loop
@@ -93,6 +161,203 @@ fn run() -> UniqueResult<()> {
         }
     }
 
+    // Rows whose column count differs from the header
+    let expected_columns: Option<usize> = header_record.as_ref().map(|h| h.len());
+    let mut column_mismatches =
+        ColumnMismatches::new(expected_columns.unwrap_or(0), arguments.max_examples);
+    let mut reject_writer: Option<BufWriter<std::fs::File>> = match &arguments.reject_file {
+        Some(path) => Some(BufWriter::new(create_file(path)?)),
+        None => None,
+    };
+
+    // Lines that could not be processed (--on-error skip|keep-raw)
+    let mut line_errors = LineErrors::new(arguments.max_examples);
+    let mut error_writer: Option<csv::Writer<std::fs::File>> = match &arguments.error_file {
+        Some(path) => Some(
+            csv::WriterBuilder::new()
+                .delimiter(b';')
+                .from_writer(create_file(path)?),
+        ),
+        None => None,
+    };
+
+    // Typed rows declared in a schema file (--schema)
+    let schema_binding: Option<SchemaBinding> = match &arguments.schema {
+        Some(path) => {
+            let header = header_record.clone().unwrap_or_default();
+            Some(Schema::load(path)?.bind(&header)?)
+        }
+        None => None,
+    };
+
+    // Header names matched to the DocsFiscais fields (case, accents, whitespace and aliases)
+    if arguments.map_docs_fiscais {
+        if let Some(header) = &header_record {
+            let mapping = map_headers(header, &DocsFiscais::headers()?, &arguments.header_alias)?;
+            print_header_mapping(&mapping, arguments.max_examples);
+
+            let required: Vec<String> = match &arguments.required_columns {
+                Some(columns) => columns
+                    .iter()
+                    .filter(|c| !c.trim().is_empty())
+                    .cloned()
+                    .collect(),
+                None => REQUIRED_COLUMNS.iter().map(|c| c.to_string()).collect(),
+            };
+            check_required_columns(&mapping.header, &required)?;
+            header_record = Some(mapping.header);
+        }
+    }
+
+    // Column names of the processed lines
+    let mut output_header: Vec<String> = if let Some(binding) = &schema_binding {
+        binding.headers()
+    } else if arguments.map_docs_fiscais {
+        DocsFiscais::headers()?
+    } else {
+        header_record.iter().flatten().map(String::from).collect()
+    };
+
+    // Per-column formatting (--format) and selected output columns (--columns, --drop-columns)
+    let input_header: Vec<String> = header_record.iter().flatten().map(String::from).collect();
+    let layout = ColumnLayout {
+        formats: resolve_formats(&arguments.format_rules, &input_header)?,
+        projection: resolve_projection(
+            &arguments.columns,
+            &arguments.drop_columns,
+            &output_header,
+        )?,
+    };
+    if let Some(columns) = &layout.projection {
+        output_header = columns.iter().map(|&i| output_header[i].clone()).collect();
+    }
+
+    // Totals per group of the printed lines (--group-by)
+    let mut aggregation: Option<Aggregation> = match arguments.group_by.is_empty() {
+        true => None,
+        false => Some(Aggregation::new(&arguments, &output_header)?),
+    };
+    let printed_header: Vec<String> = match &aggregation {
+        Some(aggregation) => aggregation.header(),
+        None => output_header.clone(),
+    };
+
+    // The header is written like the processed lines (--output-separator, --quote-style)
+    let csv_output = arguments.output_format == OutputFormat::Csv;
+    let print_header = !arguments.only_print_repeated_lines || aggregation.is_some();
+    if csv_output && header_record.is_some() && print_data && print_header {
+        let mut fields: Vec<&str> = Vec::new();
+        if arguments.annotate {
+            fields.extend(["source", "line_number"]);
+        }
+        fields.extend(printed_header.iter().map(String::as_str));
+        println!("{}", serialize_to_string(&fields, &arguments)?);
+    }
+
+    // Columns of the processed lines whose punctuation is ignored in the comparison
+    let punctuation_columns: Vec<usize> =
+        resolve_columns(&arguments.ignore_punctuation_columns, &output_header)?;
+
+    // Distinct values of the column compared by edit distance
+    let mut fuzzy_matcher: Option<(usize, FuzzyMatcher)> =
+        match (arguments.fuzzy_levenshtein, &arguments.fuzzy_column) {
+            (Some(max_distance), Some(column)) => {
+                let index = resolve_columns(std::slice::from_ref(column), &output_header)?[0];
+                Some((index, FuzzyMatcher::new(max_distance)))
+            }
+            _ => None,
+        };
+
+    // Transformation + hashing of a single line (runs in parallel)
+    let process_line =
+        |line_number: usize, line_utf8: String| -> UniqueResult<Option<(AnalyzedLine, String)>> {
+            // 1. Handle Empty Lines
+            if line_utf8.trim().is_empty() {
+                atomic_empty_lines.fetch_add(1, Ordering::Relaxed);
+                return Ok(if arguments.remove_empty_lines {
+                    None
+                } else {
+                    Some((AnalyzedLine::empty(line_number), String::new()))
+                });
+            }
+
+            // Validate NF-e keys (check digit) in the original line
+            if arguments.check {
+                let num_invalid = count_invalid_nfe_keys(&line_utf8);
+                atomic_invalid_keys.fetch_add(num_invalid, Ordering::Relaxed);
+            }
+
+            // Divert rows whose column count differs from the header (--reject-file)
+            if let (Some(expected), Some(_)) = (expected_columns, &arguments.reject_file) {
+                let num_cols = count_columns(&line_utf8, &arguments)?;
+                if num_cols != expected {
+                    let rejected = AnalyzedLine::rejected(line_number, line_utf8, num_cols);
+                    return Ok(Some((rejected, String::new())));
+                }
+            }
+
+            // 2. Handle Data Lines
+            // Choose the appropriate processing engine
+            let processed = if let Some(binding) = &schema_binding {
+                analise_line_with_schema(line_number, &line_utf8, binding, &layout, &arguments)?
+            } else if arguments.map_docs_fiscais {
+                let header = &header_record;
+                analise_line_with_serde(line_number, &line_utf8, header, &layout, &arguments)?
+            } else {
+                analise_line(&line_utf8, &layout, &arguments)?
+            };
+
+            let num_cols = processed.num_cols;
+
+            // Keep the original line to report the column inconsistency
+            let original = expected_columns
+                .is_some_and(|expected| expected != num_cols)
+                .then_some(line_utf8);
+
+            // Generate hash for deduplication
+            let filter = comparison_key(&processed.content, &arguments, &punctuation_columns)?;
+            let hash = blake3::hash(filter.as_bytes()).to_string();
+            let fingerprint = arguments.near_duplicates.map(|_| simhash(&filter));
+
+            Ok(Some((
+                AnalyzedLine {
+                    line_number,
+                    content: processed.content,
+                    column_count: num_cols,
+                    is_empty: false,
+                    fingerprint,
+                    original,
+                    is_rejected: false,
+                    error: None,
+                    values: processed.values,
+                },
+                hash,
+            )))
+        };
+
+    // Rows written to the standard output in the --output-format
+    let column_types: Option<Vec<ColumnType>> = match arguments.output_format {
+        OutputFormat::Parquet if print_data => {
+            let types = if let Some(binding) = &schema_binding {
+                binding.column_types()
+            } else if arguments.map_docs_fiscais {
+                DocsFiscais::column_types()?
+            } else {
+                vec![ColumnType::String; input_header.len()]
+            };
+            let types = match &layout.projection {
+                Some(columns) => columns.iter().map(|&i| types[i]).collect(),
+                None => types,
+            };
+            Some(match &aggregation {
+                Some(aggregation) => aggregation.column_types(&types),
+                None => types,
+            })
+        }
+        _ => None,
+    };
+    let mut row_output = RowOutput::new(&arguments, &printed_header, column_types, print_data)?;
+
     // --- STEP 2: CHUNKED PARALLEL PROCESSING ---
     while num_bytes > 0 {
         let mut vec_lines: Vec<(usize, Vec<u8>)> = Vec::with_capacity(CHUNK_SIZE);
@@ -117,42 +382,9 @@ fn run() -> UniqueResult<()> {
         let processed_chunk: UniqueResult<Vec<Option<(AnalyzedLine, String)>>> = vec_lines
             .into_par_iter() // rayon: parallel iterator
             .map(|(line_number, bytes)| {
-                let line_utf8 = get_string_utf8_from_slice_bytes(&bytes)?;
-
-                // 1. Handle Empty Lines
-                if line_utf8.trim().is_empty() {
-                    atomic_empty_lines.fetch_add(1, Ordering::Relaxed);
-                    return Ok(if arguments.remove_empty_lines {
-                        None
-                    } else {
-                        Some((AnalyzedLine::empty(line_number), String::new()))
-                    });
-                }
-
-                // 2. Handle Data Lines
-                // Choose the appropriate processing engine
-                let (content, num_cols) = if arguments.map_docs_fiscais {
-                    analise_line_with_serde(&line_utf8, &header_record, &arguments)?
-                } else {
-                    analise_line(&line_utf8, &arguments)?
-                };
-
-                // Generate hash for deduplication
-                let mut filter = content.clone();
-                if arguments.ignore_case {
-                    filter = filter.to_lowercase();
-                }
-                let hash = blake3::hash(filter.as_bytes()).to_string();
-
-                Ok(Some((
-                    AnalyzedLine {
-                        line_number,
-                        content,
-                        column_count: num_cols,
-                        is_empty: false,
-                    },
-                    hash,
-                )))
+                get_string_utf8_from_slice_bytes(&bytes)
+                    .and_then(|line_utf8| process_line(line_number, line_utf8))
+                    .or_else(|error| recover_line(&arguments, line_number, &bytes, error))
             })
             .collect();
 
@@ -160,23 +392,97 @@ fn run() -> UniqueResult<()> {
         for item in processed_chunk?.into_iter().flatten() {
             let (analyzed, hash) = item;
 
-            if uniq_hashes.insert(hash) {
+            if let Some(error) = &analyzed.error {
+                if let Some(writer) = error_writer.as_mut() {
+                    writer.serialize(error)?;
+                }
+                line_errors.add(error);
+                if !keeps_raw_lines(&arguments) {
+                    continue;
+                }
+            }
+
+            if let Some(original) = &analyzed.original {
+                column_mismatches.add(analyzed.line_number, analyzed.column_count, original);
+            }
+
+            if analyzed.is_rejected {
+                if let (Some(writer), Some(original)) = (reject_writer.as_mut(), &analyzed.original)
+                {
+                    writeln!(writer, "{original}")?;
+                }
+                column_mismatches.rejected += 1;
+                delimiter_set.insert(analyzed.column_count);
+                continue;
+            }
+
+            // Exact duplicates are detected by the hash; near-duplicates by the signature.
+            // A duplicate holds the line number of the first occurrence it matched.
+            let first_occurrence: Option<usize> = match uniq_hashes.entry(hash) {
+                Entry::Occupied(entry) => {
+                    if let Some(report) = duplicate_report.as_mut().filter(|_| !analyzed.is_empty) {
+                        report.add(*entry.get(), analyzed.line_number, &analyzed.content);
+                    }
+                    Some(*entry.get())
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(analyzed.line_number);
+                    match (near_index.as_mut(), analyzed.fingerprint) {
+                        (Some(index), Some(fingerprint)) => {
+                            index.insert(fingerprint, analyzed.line_number, &analyzed.content)
+                        }
+                        _ => None,
+                    }
+                }
+            };
+
+            if first_occurrence.is_none() {
+                num_unique_lines += 1;
                 // New unique line found
-                if !arguments.only_print_repeated_lines {
-                    println!("{}", analyzed.content);
+                if print_data && !arguments.only_print_repeated_lines {
+                    match aggregation.as_mut() {
+                        Some(aggregation) => aggregation.add(&analyzed, &arguments)?,
+                        None => row_output.write(&analyzed, None)?,
+                    }
                 }
             } else {
                 // Duplicate line found
-                if arguments.only_print_repeated_lines {
-                    println!("{}", analyzed.content);
+                if print_data && arguments.only_print_repeated_lines {
+                    match aggregation.as_mut() {
+                        Some(aggregation) => aggregation.add(&analyzed, &arguments)?,
+                        None => row_output.write(&analyzed, first_occurrence)?,
+                    }
                 }
                 num_repeated_lines += 1;
             }
 
+            if let Some((index, matcher)) = fuzzy_matcher.as_mut() {
+                if let Some(value) = split_fields(&analyzed.content, &arguments)?.get(*index) {
+                    let mut key = value.trim().to_string();
+                    if arguments.ignore_punctuation {
+                        key = remove_punctuation(&key);
+                    }
+                    matcher.add(value, normalize_key(key, &arguments));
+                }
+            }
+
             if arguments.parse_csv_file {
                 delimiter_set.insert(analyzed.column_count);
             }
         }
+
+        // One Parquet row group per processed chunk
+        row_output.end_chunk()?;
+    }
+
+    if print_data {
+        // One row per group, after all the lines were read
+        if let Some(aggregation) = &aggregation {
+            for line in aggregation.lines(&arguments)? {
+                row_output.write(&line, None)?;
+            }
+        }
+        row_output.finish()?;
     }
 
     // --- STEP 4: FINAL REPORT ---
@@ -184,114 +490,518 @@ fn run() -> UniqueResult<()> {
     // Sync the total empty lines count from the atomic counter
     let num_empty_lines = atomic_empty_lines.load(Ordering::Relaxed);
 
+    // Near-duplicate clusters report
+    if let (Some(index), Some(path)) = (&near_index, &arguments.cluster_report) {
+        index.write_report(path)?;
+    }
+
+    // Repeated keys and their line numbers
+    if let (Some(report), Some(path)) = (&duplicate_report, &arguments.report_duplicates) {
+        report.write(path, arguments.report_format)?;
+    }
+
+    // Pairs of similar values (edit distance)
+    if let Some((_, matcher)) = &fuzzy_matcher {
+        matcher.write_report(arguments.fuzzy_report.as_deref())?;
+    }
+
+    // Rows diverted from the processing
+    if let Some(writer) = reject_writer.as_mut() {
+        writer.flush()?;
+    }
+
+    // Lines that could not be processed
+    if let Some(writer) = error_writer.as_mut() {
+        writer.flush()?;
+    }
+    let line_errors = (arguments.on_error != OnError::Abort).then_some(line_errors);
+    if let Some(line_errors) = &line_errors {
+        if arguments.stats_format == StatsFormat::Text {
+            print_line_errors(&arguments, line_errors);
+        }
+    }
+
     // Final CSV structure analysis
-    analise_csv_file(&arguments, delimiter_set);
+    let csv_stats = arguments.parse_csv_file.then(|| {
+        CsvStats::new(
+            arguments.separator.char(),
+            separator_detected,
+            delimiter_set,
+            column_mismatches,
+        )
+    });
+    if let Some(csv_stats) = &csv_stats {
+        // In JSON, the CSV structure is part of the statistics
+        if arguments.stats_format == StatsFormat::Text {
+            analise_csv_file(&arguments, csv_stats);
+        }
+    }
 
     // Show statistics (Unique, Repeated, Total, Run Time)
-    print_verbose(
-        &arguments,
-        timer,
-        uniq_hashes,
-        num_repeated_lines,
-        num_empty_lines,
-    );
+    let statistics = Statistics {
+        line_errors,
+        ..Statistics::new(
+            &arguments,
+            &timer,
+            num_unique_lines,
+            num_repeated_lines,
+            num_empty_lines,
+            csv_stats,
+            arguments
+                .check
+                .then(|| atomic_invalid_keys.load(Ordering::Relaxed)),
+        )
+    };
+    statistics.emit(&arguments)?;
+
+    if arguments.check {
+        return Ok(statistics.check_status().code());
+    }
+
+    Ok(0)
+}
+
+/// Sets the values treated as null (`--null-values`, `--replace-null-values`).
+fn configure_null_values(null_values: &[String], replace: bool) {
+    if !null_values.is_empty() {
+        let mut values: Vec<String> = Vec::new();
+        if !replace {
+            values.extend(NULL_VALUES.iter().map(|s| s.to_string()));
+        }
+        values.extend(null_values.iter().cloned());
+        set_null_values(values);
+    }
+}
+
+/// Infers a schema file from the columns of a CSV file (`unique infer-schema`).
+///
+/// The schema is written to `--output` (or the standard output) and the
+/// summary of each column to the standard error.
+fn infer_schema(args: &InferSchemaArgs) -> UniqueResult<()> {
+    configure_null_values(&args.null_values, args.replace_null_values);
+
+    let mut buffer: Box<dyn BufRead> = read_file_or_stdin(&args.file)?;
+
+    let separator = match args.separator {
+        Separator::Auto => {
+            let mut sample: Vec<u8> = Vec::new();
+            for _ in 0..SAMPLE_LINES {
+                if buffer.read_until(NEWLINE_BYTE, &mut sample)? == 0 {
+                    break;
+                }
+            }
+            let separator = detect_separator(&sample).unwrap_or(DEFAULT_SEPARATOR);
+            buffer = Box::new(Cursor::new(sample).chain(buffer));
+            separator
+        }
+        Separator::Char(c) => c,
+    };
 
+    let mut inference: Option<SchemaInference> = None;
+    loop {
+        let mut bytes: Vec<u8> = Vec::new();
+        if buffer.read_until(NEWLINE_BYTE, &mut bytes)? == 0 {
+            break; // EOF
+        }
+
+        let line = get_string_utf8_from_slice_bytes(&bytes)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match inference.as_mut() {
+            None => inference = Some(SchemaInference::new(&line, separator)?),
+            Some(inference) => {
+                if args.rows.is_some_and(|rows| inference.rows >= rows) {
+                    break;
+                }
+                inference.add_line(&line)?;
+            }
+        }
+    }
+
+    let inference =
+        inference.ok_or_else(|| UniqueError::Unknown("cabeçalho não encontrado".to_string()))?;
+
+    let schema =
+        toml::to_string(&inference.schema()).map_err(|e| UniqueError::Unknown(e.to_string()))?;
+    match &args.output {
+        Some(path) => create_file(path)?.write_all(schema.as_bytes())?,
+        None => print!("{schema}"),
+    }
+
+    inference.write_summary(&mut std::io::stderr())?;
     Ok(())
 }
 
-fn analise_csv_file(args: &Arguments, delimiter_set: HashSet<usize>) {
-    let separator: char = args.separator;
+/// Writes the processed rows to the standard output in the `--output-format`.
+struct RowOutput<'a> {
+    args: &'a Arguments,
+    /// Output header (keys of the JSON objects and Parquet columns).
+    header: &'a [String],
+    /// Rows written so far (JSON array separators).
+    rows_written: usize,
+    parquet: Option<ParquetOutput<std::io::Stdout>>,
+    xlsx: Option<XlsxOutput<std::io::Stdout>>,
+}
+
+impl<'a> RowOutput<'a> {
+    /// `column_types` are only given for `--output-format parquet`.
+    fn new(
+        args: &'a Arguments,
+        header: &'a [String],
+        column_types: Option<Vec<ColumnType>>,
+        print_data: bool,
+    ) -> UniqueResult<Self> {
+        // Binary formats: the --annotate columns come first
+        let (mut names, mut types): (Vec<String>, Vec<ColumnType>) = annotation_columns(args)
+            .into_iter()
+            .map(|(name, column_type)| (name.to_string(), column_type))
+            .unzip();
+        names.extend(header.iter().cloned());
+
+        let parquet = match column_types {
+            Some(column_types) => {
+                types.extend(column_types);
+                Some(ParquetOutput::new(std::io::stdout(), &names, &types)?)
+            }
+            None => None,
+        };
+
+        let xlsx = match args.output_format {
+            OutputFormat::Xlsx if print_data => Some(XlsxOutput::new(std::io::stdout(), &names)?),
+            _ => None,
+        };
+
+        Ok(Self {
+            args,
+            header,
+            rows_written: 0,
+            parquet,
+            xlsx,
+        })
+    }
+
+    fn write(
+        &mut self,
+        analyzed: &AnalyzedLine,
+        first_occurrence: Option<usize>,
+    ) -> UniqueResult<()> {
+        let format = self.args.output_format;
+        if format != OutputFormat::Csv && analyzed.is_empty {
+            return Ok(());
+        }
+
+        match format {
+            OutputFormat::Csv => {
+                println!("{}", annotate_line(self.args, analyzed, first_occurrence)?)
+            }
+            OutputFormat::Ndjson => println!("{}", self.json_line(analyzed, first_occurrence)?),
+            OutputFormat::Json => print!(
+                "{}{}",
+                if self.rows_written == 0 { "[\n" } else { ",\n" },
+                self.json_line(analyzed, first_occurrence)?
+            ),
+            OutputFormat::Parquet | OutputFormat::Xlsx => {
+                let mut values = annotation_values(self.args, analyzed, first_occurrence);
+                values.extend(analyzed.values.iter().flatten().cloned());
+                if let Some(parquet) = self.parquet.as_mut() {
+                    parquet.push(values);
+                } else if let Some(xlsx) = self.xlsx.as_mut() {
+                    xlsx.push(&values)?;
+                }
+            }
+        }
+
+        self.rows_written += 1;
+        Ok(())
+    }
+
+    /// Ends a processed chunk (writes a Parquet row group).
+    fn end_chunk(&mut self) -> UniqueResult<()> {
+        match self.parquet.as_mut() {
+            Some(parquet) => parquet.flush(),
+            None => Ok(()),
+        }
+    }
 
-    if args.parse_csv_file {
-        // Coleta do HashSet para Vector
-        let mut delimiter_vec = delimiter_set.into_iter().collect::<Vec<usize>>();
-        delimiter_vec.sort();
+    /// Closes the JSON array or the Parquet and XLSX files.
+    fn finish(self) -> UniqueResult<()> {
+        if self.args.output_format == OutputFormat::Json {
+            println!("{}", if self.rows_written == 0 { "[]" } else { "\n]" });
+        }
+        if let Some(parquet) = self.parquet {
+            parquet.finish()?;
+        }
+        if let Some(xlsx) = self.xlsx {
+            xlsx.finish()?;
+        }
+        Ok(())
+    }
 
-        // Verifica se há mais de um número de colunas (inconsistência) ou zero colunas
-        if delimiter_vec.len() != 1 || delimiter_vec.contains(&0) {
-            eprintln!();
-            eprintln!("❌ Invalid CSV file!");
-            eprintln!("   • CSV column separator: '{}'", separator);
-            eprintln!("   • Column counts observed in rows: {:?}", delimiter_vec);
-        } else if args.verbose {
-            let first_element = delimiter_vec[0];
-            eprintln!();
-            eprintln!("✅ Valid CSV file!");
-            eprintln!("   • CSV column separator: '{}'", separator);
-            eprintln!("   • Constant column count: {}", first_element);
+    /// Formats a processed line as a JSON object keyed by the output header
+    /// (`--output-format ndjson|json`), prefixed by the `--annotate` fields.
+    ///
+    /// Lines kept unprocessed (`--on-error keep-raw`) are written as `{"raw": line}`.
+    fn json_line(
+        &self,
+        analyzed: &AnalyzedLine,
+        first_occurrence: Option<usize>,
+    ) -> UniqueResult<String> {
+        let mut keys: Vec<&str> = annotation_columns(self.args)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        let mut values = annotation_values(self.args, analyzed, first_occurrence);
+
+        match &analyzed.values {
+            Some(row) => {
+                keys.extend(self.header.iter().map(String::as_str));
+                values.extend(row.iter().cloned());
+            }
+            None => {
+                keys.push("raw");
+                values.push(TypedValue::Text(analyzed.content.clone()));
+            }
         }
+
+        serialize_to_json(&keys, &values)
     }
 }
 
-fn print_verbose(
+/// Columns added by `--annotate` to the typed output formats.
+fn annotation_columns(args: &Arguments) -> Vec<(&'static str, ColumnType)> {
+    let mut columns = Vec::new();
+    if args.annotate {
+        columns.push(("source", ColumnType::String));
+        columns.push(("line_number", ColumnType::Integer));
+        if args.only_print_repeated_lines {
+            columns.push(("first_line_number", ColumnType::Integer));
+        }
+    }
+    columns
+}
+
+/// Values of the `annotation_columns` for a processed line.
+fn annotation_values(
     args: &Arguments,
-    timer: ExecutionTime,
-    uniq_hashes: HashSet<String>,
-    num_repeated_lines: usize,
-    num_empty_lines: usize,
-) {
-    let duration = timer.get_duration();
-    let num_unique_lines = uniq_hashes.len();
-    let num_total_lines_original = num_unique_lines + num_repeated_lines;
-
-    // Cálculo da taxa de redução (deduplicação)
-    let reduction_percent = if num_total_lines_original > 0 {
-        (num_repeated_lines as f64 / num_total_lines_original as f64) * 100.0
-    } else {
-        0.0
-    };
+    analyzed: &AnalyzedLine,
+    first_occurrence: Option<usize>,
+) -> Vec<TypedValue> {
+    let mut values = Vec::new();
+    if args.annotate {
+        let source = match &args.file {
+            Some(path) => path.display().to_string(),
+            None => "stdin".to_string(),
+        };
+        values.push(TypedValue::Text(source));
+        values.push(TypedValue::Integer(analyzed.line_number as i64));
+        if args.only_print_repeated_lines {
+            values
+                .push(first_occurrence.map_or(TypedValue::Null, |n| TypedValue::Integer(n as i64)));
+        }
+    }
+    values
+}
 
-    // Cálculo de vazão (throughput)
-    let lines_per_sec = if duration.as_secs_f64() > 0.0 {
-        (num_total_lines_original as f64 / duration.as_secs_f64()) as usize
-    } else {
-        0
+/// Warns about the header columns that do not match a DocsFiscais field
+/// and about the fields that will keep their default (empty) values.
+fn print_header_mapping(mapping: &HeaderMapping, max_examples: usize) {
+    for (name, field) in &mapping.renamed {
+        eprintln!("ℹ️  Header '{name}' mapped to '{field}'");
+    }
+
+    let groups = [
+        (
+            "Headers not matching any DocsFiscais field",
+            &mapping.unmatched_headers,
+        ),
+        (
+            "DocsFiscais fields not found in the header (left empty)",
+            &mapping.missing_fields,
+        ),
+    ];
+    for (title, names) in groups {
+        if names.is_empty() {
+            continue;
+        }
+        eprintln!("⚠️  {title}: {}", names.len());
+        for name in names.iter().take(max_examples) {
+            eprintln!("     - '{name}'");
+        }
+        if names.len() > max_examples {
+            eprintln!("     ... and {} more", names.len() - max_examples);
+        }
+    }
+}
+
+/// Prefixes the output line with its source and line number (`--annotate`).
+///
+/// With `--repeated`, the line number of the first occurrence is also added.
+fn annotate_line<'a>(
+    args: &Arguments,
+    analyzed: &'a AnalyzedLine,
+    first_occurrence: Option<usize>,
+) -> UniqueResult<Cow<'a, str>> {
+    if !args.annotate {
+        return Ok(Cow::Borrowed(&analyzed.content));
+    }
+
+    let source = match &args.file {
+        Some(path) => path.display().to_string(),
+        None => "stdin".to_string(),
     };
 
-    // Linhas finais no arquivo de saída
-    let num_total_lines_final = if args.remove_empty_lines && num_empty_lines > 0 {
-        num_unique_lines.saturating_sub(1)
+    let mut fields = vec![source, analyzed.line_number.to_string()];
+    if let Some(first) = first_occurrence {
+        fields.push(first.to_string());
+    }
+
+    let annotated = if args.parse_csv_file {
+        let prefix = serialize_to_string(&fields, args)?;
+        format!(
+            "{prefix}{}{}",
+            char::from(output_delimiter(args)),
+            analyzed.content
+        )
     } else {
-        num_unique_lines
+        format!("{}:{}", fields.join(":"), analyzed.content)
     };
 
-    let max_len = num_total_lines_original.to_string().len().max(10);
+    Ok(Cow::Owned(annotated))
+}
 
-    if args.verbose {
-        eprintln!("\n{}", "=".repeat(45));
-        eprintln!("📊 EXECUTIONS SUMMARY");
-        eprintln!("{}", "=".repeat(45));
+/// Handles a line that could not be processed, according to `--on-error`.
+fn recover_line(
+    args: &Arguments,
+    line_number: usize,
+    bytes: &[u8],
+    error: UniqueError,
+) -> UniqueResult<Option<(AnalyzedLine, String)>> {
+    let line_error = LineError::new(line_number, &error);
+
+    match args.on_error {
+        // Mapping errors already identify the line
+        OnError::Abort => match error {
+            UniqueError::FieldMapping { .. } => Err(error),
+            error => Err(UniqueError::Line {
+                line_number,
+                source: Box::new(error),
+            }),
+        },
+        OnError::Skip => Ok(Some((
+            AnalyzedLine::failed(line_number, line_error),
+            String::new(),
+        ))),
+        OnError::KeepRaw => {
+            // The original line is compared and printed without transformations
+            let raw = String::from_utf8_lossy(bytes)
+                .trim_end_matches(['\r', '\n'])
+                .to_string();
+            let hash = blake3::hash(normalize_key(raw.clone(), args).as_bytes()).to_string();
+
+            let mut analyzed = AnalyzedLine::failed(line_number, line_error);
+            if args.parse_csv_file {
+                analyzed.column_count = count_columns(&raw, args).unwrap_or(0);
+            }
+            analyzed.content = raw;
+            Ok(Some((analyzed, hash)))
+        }
+    }
+}
 
-        eprintln!("📝 INPUT STATS:");
-        eprintln!(
-            "   • Total lines processed : {:>max_len$}",
-            num_total_lines_original
-        );
-        eprintln!("   • Total empty lines     : {:>max_len$}", num_empty_lines);
+/// Lines that cannot be processed are kept unchanged (`--on-error keep-raw`),
+/// except in the Parquet and XLSX outputs, whose typed columns have no place
+/// for a raw line, and in the --group-by totals.
+fn keeps_raw_lines(args: &Arguments) -> bool {
+    args.on_error == OnError::KeepRaw
+        && args.group_by.is_empty()
+        && !matches!(
+            args.output_format,
+            OutputFormat::Parquet | OutputFormat::Xlsx
+        )
+}
 
-        eprintln!("\n🔍 PROCESSING DETAILS:");
-        eprintln!(
-            "   • Unique lines found    : {:>max_len$}",
-            num_unique_lines
-        );
-        eprintln!(
-            "   • Repeated lines removed: {:>max_len$}",
-            num_repeated_lines
-        );
+/// Summary of the lines that could not be processed (`--on-error skip|keep-raw`).
+fn print_line_errors(args: &Arguments, line_errors: &LineErrors) {
+    if line_errors.total == 0 {
+        return;
+    }
+
+    let action = match args.on_error {
+        OnError::KeepRaw if keeps_raw_lines(args) => "kept unchanged",
+        OnError::KeepRaw => "skipped: no raw lines in this output",
+        _ => "skipped",
+    };
+
+    eprintln!();
+    eprintln!("⚠️  Lines with errors ({action}): {}", line_errors.total);
+    for error in &line_errors.examples {
+        match (&error.column, &error.value) {
+            (Some(column), Some(value)) => eprintln!(
+                "     - line {}: column '{}', value '{}': {}",
+                error.line_number, column, value, error.message
+            ),
+            _ => eprintln!("     - line {}: {}", error.line_number, error.message),
+        }
+    }
+    if line_errors.total > line_errors.examples.len() {
         eprintln!(
-            "   • Deduplication rate    : {:>max_len$.2}%",
-            reduction_percent
+            "     ... and {} more",
+            line_errors.total - line_errors.examples.len()
         );
+    }
+    if let Some(path) = &args.error_file {
+        eprintln!("   • Errors written to: {}", path.display());
+    }
+}
+
+fn analise_csv_file(args: &Arguments, csv_stats: &CsvStats) {
+    let separator = if csv_stats.separator_detected {
+        format!("'{}' (detected)", csv_stats.separator.escape_default())
+    } else {
+        format!("'{}'", csv_stats.separator.escape_default())
+    };
 
-        eprintln!("\n💾 OUTPUT STATS:");
+    if !csv_stats.valid {
+        eprintln!();
+        eprintln!("❌ Invalid CSV file!");
+        eprintln!("   • CSV column separator: {}", separator);
         eprintln!(
-            "   • Lines in final file   : {:>max_len$}",
-            num_total_lines_final
+            "   • Column counts observed in rows: {:?}",
+            csv_stats.column_counts
         );
 
-        eprintln!("\n⏱️  PERFORMANCE:");
-        eprintln!("   • Total run time        : {}", timer.get_elapsed_time());
-        eprintln!("   • Throughput            : {} lines/sec", lines_per_sec);
-        eprintln!("{}\n", "=".repeat(45));
+        let mismatches = &csv_stats.mismatches;
+        if mismatches.total > 0 {
+            eprintln!(
+                "   • Rows with column count different from the header ({}): {}",
+                mismatches.expected, mismatches.total
+            );
+            for example in &mismatches.examples {
+                eprintln!(
+                    "     - line {}: {} columns: {}",
+                    example.line_number, example.column_count, example.snippet
+                );
+            }
+            if mismatches.total > mismatches.examples.len() {
+                eprintln!(
+                    "     ... and {} more",
+                    mismatches.total - mismatches.examples.len()
+                );
+            }
+        }
+        if mismatches.rejected > 0 {
+            eprintln!(
+                "   • Rows written to the reject file: {}",
+                mismatches.rejected
+            );
+        }
+    } else if args.verbose {
+        let first_element = csv_stats.column_counts[0];
+        eprintln!();
+        eprintln!("✅ Valid CSV file!");
+        eprintln!("   • CSV column separator: {}", separator);
+        eprintln!("   • Constant column count: {}", first_element);
     }
 }
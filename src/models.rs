@@ -1,4 +1,4 @@
-use crate::UniqueResult;
+use crate::{LineError, TypedValue, UniqueResult};
 
 // Alias opcional para simplificar a assinatura da função
 pub type AnalysisResult = UniqueResult<Vec<Option<AnalyzedLine>>>;
@@ -17,6 +17,17 @@ pub struct AnalyzedLine {
     pub column_count: usize,
     /// Indica se a linha estava vazia antes ou após o processamento.
     pub is_empty: bool,
+    /// Assinatura SimHash da chave de comparação (apenas com `--near-duplicates`).
+    pub fingerprint: Option<u64>,
+    /// Linha original, mantida apenas quando o número de colunas difere do cabeçalho.
+    pub original: Option<String>,
+    /// Indica se a linha foi desviada para o `--reject-file`.
+    pub is_rejected: bool,
+    /// Erro ocorrido no processamento da linha (apenas com `--on-error skip|keep-raw`).
+    pub error: Option<LineError>,
+    /// Valores tipados das colunas de saída (apenas com `--output-format`
+    /// ndjson, json, parquet ou xlsx e com `--group-by`).
+    pub values: Option<Vec<TypedValue>>,
 }
 
 impl AnalyzedLine {
@@ -27,6 +38,43 @@ impl AnalyzedLine {
             content: String::new(),
             column_count: 0,
             is_empty: true,
+            fingerprint: None,
+            original: None,
+            is_rejected: false,
+            error: None,
+            values: None,
+        }
+    }
+
+    /// Cria uma linha rejeitada por ter um número de colunas diferente do cabeçalho.
+    pub fn rejected(line_number: usize, original: String, column_count: usize) -> Self {
+        Self {
+            line_number,
+            content: String::new(),
+            column_count,
+            is_empty: false,
+            fingerprint: None,
+            original: Some(original),
+            is_rejected: true,
+            error: None,
+            values: None,
+        }
+    }
+
+    /// Cria uma linha que não pôde ser processada.
+    ///
+    /// O conteúdo fica vazio; com `--on-error keep-raw`, recebe a linha original.
+    pub fn failed(line_number: usize, error: LineError) -> Self {
+        Self {
+            line_number,
+            content: String::new(),
+            column_count: 0,
+            is_empty: false,
+            fingerprint: None,
+            original: None,
+            is_rejected: false,
+            error: Some(error),
+            values: None,
         }
     }
 }
@@ -0,0 +1,290 @@
+use crate::{create_file, UniqueError, UniqueResult};
+use csv::WriterBuilder;
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::Path,
+};
+
+/// Número de bits da assinatura SimHash.
+const SIMHASH_BITS: u32 = 64;
+
+/// Tamanho (em caracteres) dos fragmentos usados como atributos do SimHash.
+const SHINGLE_SIZE: usize = 3;
+
+/// Calcula a assinatura SimHash (64 bits) de um texto.
+///
+/// Os atributos são fragmentos de 3 caracteres (shingles), de modo que
+/// um erro de digitação ou uma palavra a mais alteram poucos bits.
+///
+/// Example:
+/// ```
+/// use unique::{simhash, similarity};
+/// let a = simhash("Parafuso sextavado aço inox 10mm");
+/// let b = simhash("Parafuso sextavado aco inox 10mm");
+/// let c = simhash("Óleo diesel S10 comum");
+/// assert!(similarity(a, b) > similarity(a, c));
+/// ```
+pub fn simhash(text: &str) -> u64 {
+    let chars: Vec<char> = text.chars().collect();
+    let mut weights = [0i32; SIMHASH_BITS as usize];
+
+    let mut add_feature = |feature: &[char]| {
+        let mut hasher = DefaultHasher::new();
+        feature.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash >> bit & 1 == 1 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    };
+
+    if chars.len() <= SHINGLE_SIZE {
+        add_feature(&chars);
+    } else {
+        chars.windows(SHINGLE_SIZE).for_each(add_feature);
+    }
+
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, &weight)| weight > 0)
+        .fold(0u64, |fingerprint, (bit, _)| fingerprint | 1 << bit)
+}
+
+/// Similaridade entre duas assinaturas: 1 - (distância de Hamming / 64).
+pub fn similarity(a: u64, b: u64) -> f64 {
+    1.0 - (a ^ b).count_ones() as f64 / SIMHASH_BITS as f64
+}
+
+/// Linha agrupada em um cluster de quase-duplicatas.
+#[derive(Debug, Clone)]
+pub struct ClusterMember {
+    pub line_number: usize,
+    pub similarity: f64,
+    pub content: String,
+}
+
+/// Grupo de linhas semelhantes representado pela primeira ocorrência.
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    pub fingerprint: u64,
+    pub representative: ClusterMember,
+    pub members: Vec<ClusterMember>,
+}
+
+/// Índice LSH (Locality-Sensitive Hashing) das assinaturas SimHash.
+///
+/// A assinatura é dividida em `max_distance + 1` faixas de bits: pelo
+/// princípio da casa dos pombos, duas assinaturas com distância de Hamming
+/// até `max_distance` coincidem em pelo menos uma faixa. Assim, apenas os
+/// clusters que compartilham alguma faixa são comparados.
+#[derive(Debug)]
+pub struct NearDuplicateIndex {
+    threshold: f64,
+    keep_members: bool,
+    bands: Vec<(u32, u64)>,
+    buckets: Vec<HashMap<u64, Vec<usize>>>,
+    clusters: Vec<Cluster>,
+}
+
+impl NearDuplicateIndex {
+    /// Cria o índice para o limiar de similaridade informado (entre 0.0 e 1.0).
+    ///
+    /// Com `keep_members`, as linhas agrupadas são preservadas para o relatório.
+    pub fn new(threshold: f64, keep_members: bool) -> Self {
+        let max_distance = ((1.0 - threshold) * SIMHASH_BITS as f64).floor() as u32;
+        let num_bands = (max_distance + 1).min(SIMHASH_BITS);
+
+        let bands: Vec<(u32, u64)> = (0..num_bands)
+            .map(|band| {
+                let start = band * SIMHASH_BITS / num_bands;
+                let end = (band + 1) * SIMHASH_BITS / num_bands;
+                let width = end - start;
+                let mask = if width == SIMHASH_BITS {
+                    u64::MAX
+                } else {
+                    (1u64 << width) - 1
+                };
+                (start, mask)
+            })
+            .collect();
+
+        Self {
+            threshold,
+            keep_members,
+            buckets: vec![HashMap::new(); bands.len()],
+            bands,
+            clusters: Vec::new(),
+        }
+    }
+
+    /// Procura um cluster semelhante à linha.
+    ///
+    /// Se a linha for uma quase-duplicata de um cluster existente, retorna o
+    /// número da linha representante. Caso contrário, a linha passa a
+    /// representar um novo cluster e retorna `None`.
+    pub fn insert(&mut self, fingerprint: u64, line_number: usize, content: &str) -> Option<usize> {
+        let best_match = self
+            .bands
+            .iter()
+            .zip(&self.buckets)
+            .filter_map(|(&(shift, mask), bucket)| bucket.get(&(fingerprint >> shift & mask)))
+            .flatten()
+            .map(|&id| (id, similarity(fingerprint, self.clusters[id].fingerprint)))
+            .filter(|&(_, sim)| sim >= self.threshold)
+            .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)));
+
+        if let Some((id, sim)) = best_match {
+            let cluster = &mut self.clusters[id];
+            if self.keep_members {
+                cluster.members.push(ClusterMember {
+                    line_number,
+                    similarity: sim,
+                    content: content.to_string(),
+                });
+            }
+            return Some(cluster.representative.line_number);
+        }
+
+        let id = self.clusters.len();
+        for (&(shift, mask), bucket) in self.bands.iter().zip(self.buckets.iter_mut()) {
+            bucket
+                .entry(fingerprint >> shift & mask)
+                .or_default()
+                .push(id);
+        }
+
+        self.clusters.push(Cluster {
+            fingerprint,
+            representative: ClusterMember {
+                line_number,
+                similarity: 1.0,
+                content: if self.keep_members {
+                    content.to_string()
+                } else {
+                    String::new()
+                },
+            },
+            members: Vec::new(),
+        });
+        None
+    }
+
+    /// Clusters com pelo menos uma quase-duplicata.
+    pub fn clusters(&self) -> impl Iterator<Item = &Cluster> {
+        self.clusters.iter().filter(|c| !c.members.is_empty())
+    }
+
+    /// Grava o relatório de clusters em formato CSV (separador ';').
+    ///
+    /// Cada linha do relatório contém o cluster, o número da linha
+    /// representante, o número da linha agrupada, a similaridade e o conteúdo.
+    pub fn write_report(&self, path: &Path) -> UniqueResult<()> {
+        let file_error = |e: std::io::Error| UniqueError::FileError {
+            path: path.display().to_string(),
+            source: e,
+        };
+
+        let file = create_file(path)?;
+        let mut wtr = WriterBuilder::new().delimiter(b';').from_writer(file);
+
+        wtr.write_record([
+            "cluster",
+            "representative",
+            "line_number",
+            "similarity",
+            "content",
+        ])?;
+
+        for (id, cluster) in self.clusters().enumerate() {
+            let representative = cluster.representative.line_number.to_string();
+            let all = std::iter::once(&cluster.representative).chain(&cluster.members);
+
+            for member in all {
+                wtr.write_record([
+                    (id + 1).to_string(),
+                    representative.clone(),
+                    member.line_number.to_string(),
+                    format!("{:.4}", member.similarity),
+                    member.content.clone(),
+                ])?;
+            }
+        }
+
+        wtr.flush().map_err(file_error)?;
+        Ok(())
+    }
+}
+
+/// Valida o limiar de similaridade informado na linha de comando.
+pub fn parse_threshold(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(threshold) if (0.0..=1.0).contains(&threshold) => Ok(threshold),
+        _ => Err(format!("'{value}' must be a number between 0.0 and 1.0")),
+    }
+}
+
+/// cargo test -- --show-output tests_near_duplicates
+#[cfg(test)]
+mod tests_near_duplicates {
+    use super::*;
+
+    #[test]
+    fn test_simhash_similarity() {
+        let a = simhash("PARAFUSO SEXTAVADO ACO INOX M10 X 50MM");
+        let b = simhash("PARAFUSO SEXTAVADO ACO INOX M10 X 50 MM");
+        let c = simhash("OLEO LUBRIFICANTE MOTOR 15W40 BALDE 20L");
+
+        println!("sim(a, b) = {:.4}", similarity(a, b));
+        println!("sim(a, c) = {:.4}", similarity(a, c));
+
+        assert_eq!(similarity(a, a), 1.0);
+        assert!(similarity(a, b) >= 0.85);
+        assert!(similarity(a, c) < 0.85);
+    }
+
+    #[test]
+    fn test_index_groups_near_duplicates() {
+        let lines = [
+            "PARAFUSO SEXTAVADO ACO INOX M10 X 50MM",
+            "OLEO LUBRIFICANTE MOTOR 15W40 BALDE 20L",
+            "PARAFUSO SEXTAVADO ACO INOX M10 X 50 MM",
+            "PARAFUZO SEXTAVADO ACO INOX M10 X 50MM",
+        ];
+
+        let mut index = NearDuplicateIndex::new(0.85, true);
+        let near: Vec<Option<usize>> = lines
+            .iter()
+            .enumerate()
+            .map(|(n, line)| index.insert(simhash(line), n + 1, line))
+            .collect();
+
+        assert_eq!(near, vec![None, None, Some(1), Some(1)]);
+
+        let clusters: Vec<&Cluster> = index.clusters().collect();
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].representative.line_number, 1);
+        assert_eq!(clusters[0].members.len(), 2);
+    }
+
+    #[test]
+    fn test_threshold_one_only_matches_equal_signatures() {
+        let mut index = NearDuplicateIndex::new(1.0, false);
+        assert_eq!(index.insert(0b1010, 1, ""), None);
+        assert_eq!(index.insert(0b1010, 2, ""), Some(1));
+        assert_eq!(index.insert(0b1011, 3, ""), None);
+    }
+
+    #[test]
+    fn test_parse_threshold() {
+        assert_eq!(parse_threshold("0.9"), Ok(0.9));
+        assert_eq!(parse_threshold("1"), Ok(1.0));
+        assert!(parse_threshold("1.5").is_err());
+        assert!(parse_threshold("abc").is_err());
+    }
+}
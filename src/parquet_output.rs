@@ -0,0 +1,260 @@
+use crate::{ColumnType, TypedValue, UniqueResult};
+use arrow_array::{
+    builder::{Date32Builder, Decimal128Builder, Float64Builder, Int64Builder, StringBuilder},
+    ArrayRef, RecordBatch,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use chrono::NaiveDate;
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use rust_decimal::Decimal;
+use std::{io::Write, sync::Arc};
+
+/// Precisão e casas decimais das colunas `Decimal128` (somas de `--sum`):
+/// 38 dígitos comportam os 29 dígitos inteiros de um `Decimal`.
+const DECIMAL_PRECISION: u8 = 38;
+const DECIMAL_SCALE: i8 = 9;
+
+/// Tipo Arrow de uma coluna (`Date32`, `Decimal128`, `Float64`, `Int64` ou `Utf8`).
+fn data_type(column_type: ColumnType) -> DataType {
+    match column_type {
+        ColumnType::String => DataType::Utf8,
+        ColumnType::Integer => DataType::Int64,
+        ColumnType::Float => DataType::Float64,
+        ColumnType::Date => DataType::Date32,
+        ColumnType::Decimal => DataType::Decimal128(DECIMAL_PRECISION, DECIMAL_SCALE),
+    }
+}
+
+/// Valor de uma coluna `Decimal128`: o decimal com `DECIMAL_SCALE` casas
+/// (arredondado, se tiver mais).
+fn decimal_value(value: Decimal) -> i128 {
+    let mut value = value.round_dp(DECIMAL_SCALE as u32);
+    value.rescale(DECIMAL_SCALE as u32);
+    value.mantissa()
+}
+
+/// Gravação das linhas tipadas em Parquet (`--output-format parquet`).
+///
+/// As linhas são acumuladas e gravadas em um grupo de linhas (row group)
+/// a cada chamada de `flush`.
+pub struct ParquetOutput<W: Write + Send> {
+    writer: ArrowWriter<W>,
+    schema: SchemaRef,
+    types: Vec<ColumnType>,
+    rows: Vec<Vec<TypedValue>>,
+}
+
+impl<W: Write + Send> ParquetOutput<W> {
+    /// Cria o arquivo com uma coluna (anulável) para cada nome do cabeçalho.
+    pub fn new(output: W, header: &[String], types: &[ColumnType]) -> UniqueResult<Self> {
+        let fields: Vec<Field> = header
+            .iter()
+            .zip(types)
+            .map(|(name, &column_type)| Field::new(name, data_type(column_type), true))
+            .collect();
+        let schema: SchemaRef = Arc::new(Schema::new(fields));
+
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+
+        Ok(Self {
+            writer: ArrowWriter::try_new(output, schema.clone(), Some(properties))?,
+            schema,
+            types: types.to_vec(),
+            rows: Vec::new(),
+        })
+    }
+
+    /// Acrescenta uma linha (valores na ordem do cabeçalho).
+    pub fn push(&mut self, values: Vec<TypedValue>) {
+        self.rows.push(values);
+    }
+
+    /// Grava as linhas acumuladas em um novo grupo de linhas.
+    pub fn flush(&mut self) -> UniqueResult<()> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+
+        let columns: Vec<ArrayRef> = self
+            .types
+            .iter()
+            .enumerate()
+            .map(|(index, &column_type)| self.column(index, column_type))
+            .collect();
+
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        self.writer.write(&batch)?;
+        self.writer.flush()?;
+        self.rows.clear();
+        Ok(())
+    }
+
+    /// Grava as linhas restantes e o rodapé do arquivo.
+    pub fn finish(mut self) -> UniqueResult<()> {
+        self.flush()?;
+        self.writer.close()?;
+        Ok(())
+    }
+
+    /// Monta a coluna `index` das linhas acumuladas.
+    ///
+    /// Valores de outro tipo são gravados como nulos. Linhas com erro não
+    /// chegam aqui: com `--on-error keep-raw`, elas são descartadas.
+    fn column(&self, index: usize, column_type: ColumnType) -> ArrayRef {
+        let values = self.rows.iter().map(|row| row.get(index));
+
+        match column_type {
+            ColumnType::String => {
+                let mut builder = StringBuilder::new();
+                for value in values {
+                    match value {
+                        Some(TypedValue::Null) | None => builder.append_null(),
+                        Some(other) => builder.append_value(other.to_string()),
+                    }
+                }
+                Arc::new(builder.finish())
+            }
+            ColumnType::Integer => {
+                let mut builder = Int64Builder::new();
+                for value in values {
+                    builder.append_option(match value {
+                        Some(TypedValue::Integer(n)) => Some(*n),
+                        _ => None,
+                    });
+                }
+                Arc::new(builder.finish())
+            }
+            ColumnType::Float => {
+                let mut builder = Float64Builder::new();
+                for value in values {
+                    builder.append_option(match value {
+                        Some(TypedValue::Float(n)) => Some(*n),
+                        Some(TypedValue::Integer(n)) => Some(*n as f64),
+                        _ => None,
+                    });
+                }
+                Arc::new(builder.finish())
+            }
+            ColumnType::Decimal => {
+                let mut builder = Decimal128Builder::new();
+                for value in values {
+                    builder.append_option(match value {
+                        Some(TypedValue::Decimal(n)) => Some(decimal_value(*n)),
+                        Some(TypedValue::Integer(n)) => Some(decimal_value(Decimal::from(*n))),
+                        _ => None,
+                    });
+                }
+                let array = builder
+                    .finish()
+                    .with_precision_and_scale(DECIMAL_PRECISION, DECIMAL_SCALE)
+                    .expect("precisão e escala Decimal128 válidas");
+                Arc::new(array)
+            }
+            ColumnType::Date => {
+                let mut builder = Date32Builder::new();
+                for value in values {
+                    builder.append_option(match value {
+                        Some(TypedValue::Date(date)) => Some(days_since_epoch(*date)),
+                        _ => None,
+                    });
+                }
+                Arc::new(builder.finish())
+            }
+        }
+    }
+}
+
+/// Dias desde 1970-01-01 (representação de `Date32`).
+fn days_since_epoch(date: NaiveDate) -> i32 {
+    (date - NaiveDate::default()).num_days() as i32
+}
+
+/// cargo test -- --show-output tests_parquet_output
+#[cfg(test)]
+mod tests_parquet_output {
+    use super::*;
+    use arrow_array::{Array, Date32Array, Decimal128Array, Float64Array, Int64Array, StringArray};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_write_typed_rows() {
+        let header = vec![
+            "CNPJ".to_string(),
+            "CFOP".to_string(),
+            "Valor".to_string(),
+            "Emissão".to_string(),
+            "sum(Valor)".to_string(),
+        ];
+        let types = [
+            ColumnType::String,
+            ColumnType::Integer,
+            ColumnType::Float,
+            ColumnType::Date,
+            ColumnType::Decimal,
+        ];
+        let sum = Decimal::from_str("12345678901234567.90").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+
+        let name = format!("tests_parquet_output_{}.parquet", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let file = std::fs::File::create(&path).unwrap();
+        let mut output = ParquetOutput::new(file, &header, &types).unwrap();
+        output.push(vec![
+            TypedValue::Text("12.345.678/0001-99".to_string()),
+            TypedValue::Integer(5102),
+            TypedValue::Float(1234.5),
+            TypedValue::Date(date),
+            TypedValue::Decimal(sum),
+        ]);
+        output.flush().unwrap();
+        output.push(vec![TypedValue::Null; 5]);
+        output.finish().unwrap();
+
+        let file = std::fs::File::open(&path).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        // Um grupo de linhas por chamada de flush
+        assert_eq!(reader.metadata().num_row_groups(), 2);
+        assert_eq!(reader.schema().field(3).data_type(), &DataType::Date32);
+
+        let batches: Vec<RecordBatch> = reader.build().unwrap().map(Result::unwrap).collect();
+        std::fs::remove_file(&path).unwrap();
+        let first = &batches[0];
+        let column = |i: usize| first.column(i).as_any();
+
+        assert_eq!(
+            column(0).downcast_ref::<StringArray>().unwrap().value(0),
+            "12.345.678/0001-99"
+        );
+        assert_eq!(
+            column(1).downcast_ref::<Int64Array>().unwrap().value(0),
+            5102
+        );
+        assert_eq!(
+            column(2).downcast_ref::<Float64Array>().unwrap().value(0),
+            1234.5
+        );
+        assert_eq!(
+            column(3)
+                .downcast_ref::<Date32Array>()
+                .unwrap()
+                .value_as_date(0),
+            Some(date)
+        );
+        // A soma mantém todos os dígitos
+        assert_eq!(
+            column(4)
+                .downcast_ref::<Decimal128Array>()
+                .unwrap()
+                .value_as_string(0),
+            "12345678901234567.900000000"
+        );
+        // A segunda linha só tem nulos
+        let rows: usize = batches.iter().map(RecordBatch::num_rows).sum();
+        let last = batches.last().unwrap();
+        assert_eq!(rows, 2);
+        assert!(last.column(2).is_null(last.num_rows() - 1));
+    }
+}
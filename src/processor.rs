@@ -1,13 +1,62 @@
 use crate::{
-    format_date, format_key, format_number, Arguments, DocsFiscais, UniqueError, UniqueResult,
+    format_date, format_key, format_number, is_null_val, Arguments, DocsFiscais, FormatKind,
+    OutputFormat, QuoteStyle, SchemaBinding, TypedValue, UniqueError, UniqueResult, DATA_FORMAT,
 };
+use chrono::NaiveDate;
 use claudiofsr_lib::StrExtension;
 use csv::{ReaderBuilder, StringRecord, WriterBuilder};
 
+/// Field delimiter used for the processed CSV lines (`--output-separator`).
+///
+/// Defaults to the input separator, so header and lines stay consistent.
+pub fn output_delimiter(args: &Arguments) -> u8 {
+    args.output_separator.unwrap_or(args.separator.char()) as u8
+}
+
+/// Column layout resolved from the CSV header.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnLayout {
+    /// Formatting of each input column (`--format`, see `resolve_formats`).
+    pub formats: Vec<Vec<FormatKind>>,
+    /// Selected output columns, in order (see `resolve_projection`).
+    pub projection: Option<Vec<usize>>,
+}
+
+impl ColumnLayout {
+    /// Formatting rules of the input column.
+    fn formats(&self, index: usize) -> &[FormatKind] {
+        self.formats.get(index).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Result of the analysis of a CSV line.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessedLine {
+    /// The processed CSV line (also used for the comparison).
+    pub content: String,
+    /// Number of columns of the input line.
+    pub num_cols: usize,
+    /// Typed values of the output columns (only with the typed `--output-format`s
+    /// ndjson, json, parquet and xlsx, and with `--group-by`; see `typed_output`).
+    pub values: Option<Vec<TypedValue>>,
+}
+
+/// Typed values are only built for the typed output formats and `--group-by`.
+fn typed_output(args: &Arguments) -> bool {
+    args.output_format != OutputFormat::Csv || !args.group_by.is_empty()
+}
+
 /// Simple line analysis: iterates through columns and applies formatting.
-pub fn analise_line(line: &str, args: &Arguments) -> UniqueResult<(String, usize)> {
+///
+/// With typed output (see `typed_output`), the values are text (or null),
+/// except in the columns formatted as numbers or dates.
+pub fn analise_line(
+    line: &str,
+    layout: &ColumnLayout,
+    args: &Arguments,
+) -> UniqueResult<ProcessedLine> {
     let mut reader = ReaderBuilder::new()
-        .delimiter(args.separator as u8)
+        .delimiter(args.separator.byte())
         .has_headers(false)
         .from_reader(line.as_bytes());
 
@@ -15,17 +64,87 @@ pub fn analise_line(line: &str, args: &Arguments) -> UniqueResult<(String, usize
         let record = result?;
         let num_cols = record.len();
 
-        // Apply formatting to each column
+        // Apply formatting to each column (null values are written as --null-output)
         let formatted_cols: Vec<String> = record
             .iter()
-            .map(|col| apply_formatting(col, args))
+            .enumerate()
+            .map(|(index, col)| match null_output(col, args) {
+                Some(null) => null.to_string(),
+                None => apply_formatting(col, layout.formats(index), args),
+            })
             .collect();
 
-        let modified = serialize_to_string(&formatted_cols)?;
-        return Ok((post_process_string(modified, args), num_cols));
+        let values = typed_output(args).then(|| {
+            let typed = record
+                .iter()
+                .zip(&formatted_cols)
+                .enumerate()
+                .map(|(index, (col, formatted))| {
+                    typed_cell(col, formatted, layout.formats(index), args)
+                })
+                .collect();
+            project(typed, layout)
+        });
+
+        let modified = serialize_to_string(&project(formatted_cols, layout), args)?;
+        return Ok(ProcessedLine {
+            content: post_process_string(modified, args),
+            num_cols,
+            values,
+        });
+    }
+
+    Ok(ProcessedLine {
+        content: line.to_string(),
+        ..ProcessedLine::default()
+    })
+}
+
+/// Typed value of a formatted cell (`--output-format` other than csv).
+///
+/// Numbers and dates are typed only in the columns formatted with
+/// `-f`, `-d` or `--format`; NF-e keys lose the quotes added by `format_key`.
+fn typed_cell(raw: &str, formatted: &str, formats: &[FormatKind], args: &Arguments) -> TypedValue {
+    if raw.trim().is_empty() || is_null_val(raw) {
+        return TypedValue::Null;
+    }
+
+    if args.format_number || formats.contains(&FormatKind::Number) {
+        if let Ok(number) = formatted.parse::<f64>() {
+            return TypedValue::Float(number);
+        }
+    }
+    if args.format_date || formats.contains(&FormatKind::Date) {
+        if let Ok(date) = NaiveDate::parse_from_str(formatted, DATA_FORMAT) {
+            return TypedValue::Date(date);
+        }
+    }
+    if args.format_key || formats.contains(&FormatKind::NfeKey) {
+        let key = formatted.trim_matches('\'');
+        if key.len() == 44 && key.chars().all(|c| c.is_ascii_digit()) {
+            return TypedValue::Text(key.to_string());
+        }
+    }
+
+    TypedValue::Text(formatted.to_string())
+}
+
+/// Parses a raw CSV line (e.g. the header) into its fields.
+pub fn parse_record(line: &str, args: &Arguments) -> UniqueResult<StringRecord> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(args.separator.byte())
+        .has_headers(false)
+        .from_reader(line.as_bytes());
+
+    match reader.records().next() {
+        Some(result) => Ok(result?),
+        None => Ok(StringRecord::new()),
     }
+}
 
-    Ok((line.to_string(), 0))
+/// Counts the columns of a raw CSV line without applying any formatting.
+pub fn count_columns(line: &str, args: &Arguments) -> UniqueResult<usize> {
+    Ok(parse_record(line, args)?.len())
 }
 
 /// Robust line analysis using the `DocsFiscais` struct mapping via Serde.
@@ -33,23 +152,28 @@ pub fn analise_line(line: &str, args: &Arguments) -> UniqueResult<(String, usize
 /// This function uses a pre-parsed `header_record` to map CSV columns to
 /// struct fields by name. It is significantly faster than creating
 /// virtual CSV strings for every line.
+///
+/// `line_number` identifies the line in the mapping errors and
+/// `layout` selects and orders the output columns.
 pub fn analise_line_with_serde(
+    line_number: usize,
     line: &str,
     header_record: &Option<StringRecord>,
+    layout: &ColumnLayout,
     args: &Arguments,
-) -> UniqueResult<(String, usize)> {
+) -> UniqueResult<ProcessedLine> {
     // 1. Parse the raw line into a CSV StringRecord
     let mut reader = ReaderBuilder::new()
         .quoting(true)
         .double_quote(true)
         .has_headers(false)
         .trim(csv::Trim::All)
-        .delimiter(args.separator as u8)
+        .delimiter(args.separator.byte())
         .from_reader(line.as_bytes());
 
     let record = match reader.records().next() {
         Some(res) => res?,
-        None => return Ok((String::new(), 0)),
+        None => return Ok(ProcessedLine::default()),
     };
 
     let num_cols = record.len();
@@ -58,35 +182,197 @@ pub fn analise_line_with_serde(
     // We pass the header as context so Serde knows which column is which
     let doc: DocsFiscais = record
         .deserialize(header_record.as_ref())
-        .map_err(|e| UniqueError::Mapping(format!("Serde mapping failed: {}", e)))?;
+        .map_err(|e| mapping_error(e, line_number, &record, header_record))?;
 
     // 3. Serialize the struct back to a clean semicolon-separated string
-    let processed_line = serialize_to_string(&doc)?;
-    Ok((post_process_string(processed_line, args), num_cols))
+    let processed_line = if layout.projection.is_some() || rewrites_nulls(args) {
+        serialize_to_string(&project(write_nulls(to_fields(&doc)?, args), layout), args)?
+    } else {
+        serialize_to_string(&doc, args)?
+    };
+
+    let values = match typed_output(args) {
+        true => Some(project(doc.typed_values()?, layout)),
+        false => None,
+    };
+
+    Ok(ProcessedLine {
+        content: post_process_string(processed_line, args),
+        num_cols,
+        values,
+    })
+}
+
+/// Serializes a struct into its list of fields (one per column).
+fn to_fields<T: serde::Serialize>(data: &T) -> UniqueResult<Vec<String>> {
+    let mut wtr = WriterBuilder::new().has_headers(false).from_writer(vec![]);
+    wtr.serialize(data)?;
+    let bytes = wtr.into_inner().map_err(|e| e.into_error())?;
+
+    let mut reader = ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(bytes.as_slice());
+
+    match reader.records().next() {
+        Some(result) => Ok(result?.iter().map(String::from).collect()),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Null values are rewritten only with `--null-values` or `--null-output`;
+/// otherwise the cells are written as they are.
+fn rewrites_nulls(args: &Arguments) -> bool {
+    !args.null_values.is_empty() || args.null_output.is_some()
+}
+
+/// The text written in place of a null cell (in the null list, see `is_null_val`),
+/// or `None` if the cell is not null or nulls are not rewritten.
+///
+/// Empty cells are null only while "" is in the list: `--replace-null-values`
+/// without an empty value keeps them as they are.
+fn null_output<'a>(cell: &str, args: &'a Arguments) -> Option<&'a str> {
+    (rewrites_nulls(args) && is_null_val(cell))
+        .then(|| args.null_output.as_deref().unwrap_or_default())
+}
+
+/// Writes the null cells as `--null-output`.
+fn write_nulls(fields: Vec<String>, args: &Arguments) -> Vec<String> {
+    if !rewrites_nulls(args) {
+        return fields;
+    }
+    fields
+        .into_iter()
+        .map(|field| match null_output(&field, args) {
+            Some(null) => null.to_string(),
+            None => field,
+        })
+        .collect()
+}
+
+/// Keeps only the selected columns, in the selected order.
+fn project<T: Clone + Default>(fields: Vec<T>, layout: &ColumnLayout) -> Vec<T> {
+    match &layout.projection {
+        Some(columns) => columns
+            .iter()
+            .map(|&index| fields.get(index).cloned().unwrap_or_default())
+            .collect(),
+        None => fields,
+    }
+}
+
+/// Typed line analysis using a schema file (`--schema`).
+///
+/// Like `analise_line_with_serde`, the row is validated and normalized,
+/// but the columns and their types are declared at runtime.
+pub fn analise_line_with_schema(
+    line_number: usize,
+    line: &str,
+    binding: &SchemaBinding,
+    layout: &ColumnLayout,
+    args: &Arguments,
+) -> UniqueResult<ProcessedLine> {
+    let mut reader = ReaderBuilder::new()
+        .has_headers(false)
+        .trim(csv::Trim::All)
+        .delimiter(args.separator.byte())
+        .from_reader(line.as_bytes());
+
+    let record = match reader.records().next() {
+        Some(res) => res?,
+        None => return Ok(ProcessedLine::default()),
+    };
+
+    let row = binding.parse_row(line_number, &record)?;
+    let fields = project(write_nulls(binding.render(&row), args), layout);
+    let processed_line = serialize_to_string(&fields, args)?;
+
+    Ok(ProcessedLine {
+        content: post_process_string(processed_line, args),
+        num_cols: record.len(),
+        values: typed_output(args).then(|| project(row, layout)),
+    })
+}
+
+/// Converts a deserialization failure into `UniqueError::FieldMapping`,
+/// identifying the column, the raw value and the expected type.
+///
+/// The typed deserializers of `DocsFiscais` do not report the failing field,
+/// so the first cell rejected by its column type (`DocsFiscais::expected_type`)
+/// is reported. This only runs on the error path.
+fn mapping_error(
+    error: csv::Error,
+    line_number: usize,
+    record: &StringRecord,
+    header_record: &Option<StringRecord>,
+) -> UniqueError {
+    let failure = header_record.as_ref().and_then(|header| {
+        header.iter().zip(record.iter()).find_map(|(name, value)| {
+            let expected = DocsFiscais::expected_type(name)?;
+            (!expected.accepts(value)).then_some((name, value, expected))
+        })
+    });
+
+    match failure {
+        Some((column, value, expected)) => UniqueError::FieldMapping {
+            line_number,
+            column: column.to_string(),
+            value: value.to_string(),
+            expected,
+        },
+        None => UniqueError::Mapping(format!("Serde mapping failed: {}", error)),
+    }
 }
 
 /// Applies all enabled formatting rules to a single CSV cell.
 ///
-/// This is the central point for data transformation based on CLI arguments.
-fn apply_formatting(col: &str, args: &Arguments) -> String {
+/// This is the central point for data transformation based on CLI arguments:
+/// the global flags apply to every cell and `formats` (`--format`) to this column.
+fn apply_formatting(col: &str, formats: &[FormatKind], args: &Arguments) -> String {
     let mut s = col.replace("\\n", " ");
 
-    if args.format_date {
+    if args.format_date || formats.contains(&FormatKind::Date) {
         s = format_date(s);
     }
-    if args.format_key {
+    if args.format_key || formats.contains(&FormatKind::NfeKey) {
         s = format_key(s);
     }
-    if args.format_number {
+    if args.format_number || formats.contains(&FormatKind::Number) {
         s = format_number(s, args.number_format);
     }
     s
 }
 
-/// Helper to serialize a record or struct into a semicolon-delimited String.
-fn serialize_to_string<T: serde::Serialize>(data: &T) -> UniqueResult<String> {
+/// Serializes a row as a JSON object: the keys are the column names
+/// and the values keep their types (`--output-format ndjson|json`).
+pub fn serialize_to_json(keys: &[&str], values: &[TypedValue]) -> UniqueResult<String> {
+    struct JsonRow<'a>(&'a [&'a str], &'a [TypedValue]);
+
+    impl serde::Serialize for JsonRow<'_> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            // Keeps the column order (a map would sort the keys)
+            serializer.collect_map(self.0.iter().zip(self.1))
+        }
+    }
+
+    serde_json::to_string(&JsonRow(keys, values)).map_err(|e| UniqueError::Unknown(e.to_string()))
+}
+
+/// Helper to serialize a record or struct into a line of the CSV output,
+/// using `--output-separator` and `--quote-style`.
+pub fn serialize_to_string<T: serde::Serialize>(
+    data: &T,
+    args: &Arguments,
+) -> UniqueResult<String> {
+    let quote_style = match args.quote_style {
+        QuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+        QuoteStyle::Always => csv::QuoteStyle::Always,
+        QuoteStyle::Never => csv::QuoteStyle::Never,
+        QuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+    };
+
     let mut wtr = WriterBuilder::new()
-        .delimiter(b';')
+        .delimiter(output_delimiter(args))
+        .quote_style(quote_style)
         .has_headers(false)
         .flexible(false)
         .from_writer(vec![]);
@@ -97,6 +383,20 @@ fn serialize_to_string<T: serde::Serialize>(data: &T) -> UniqueResult<String> {
     Ok(String::from_utf8(bytes)?.trim_end().to_string())
 }
 
+/// Splits a processed line (see `serialize_to_string`) back into its fields.
+pub fn split_fields(line: &str, args: &Arguments) -> UniqueResult<Vec<String>> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(output_delimiter(args))
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(line.as_bytes());
+
+    match reader.records().next() {
+        Some(result) => Ok(result?.iter().map(String::from).collect()),
+        None => Ok(Vec::new()),
+    }
+}
+
 /// Final cleanup: handles whitespace replacement and trimming.
 fn post_process_string(mut s: String, args: &Arguments) -> String {
     if args.replace_multiple_whitespaces {
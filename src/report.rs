@@ -0,0 +1,120 @@
+use crate::{create_file, ReportFormat, UniqueError, UniqueResult};
+use csv::WriterBuilder;
+use serde::Serialize;
+use std::{collections::BTreeMap, io::Write, path::Path};
+
+/// Ocorrências de uma mesma chave de comparação.
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateGroup {
+    /// Conteúdo da primeira repetição (mesma chave da primeira ocorrência).
+    pub key: String,
+    /// Número de ocorrências.
+    pub count: usize,
+    /// Números das linhas originais, em ordem crescente.
+    pub line_numbers: Vec<usize>,
+}
+
+/// Relatório de duplicatas: agrupa os números de linha das chaves repetidas,
+/// na ordem da primeira ocorrência.
+///
+/// Apenas as chaves repetidas são guardadas: a primeira ocorrência de cada
+/// chave já está no mapa de deduplicação (hash => número da linha).
+#[derive(Debug, Default)]
+pub struct DuplicateReport {
+    /// Linha da primeira ocorrência => grupo.
+    groups: BTreeMap<usize, DuplicateGroup>,
+}
+
+impl DuplicateReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registra uma repetição da chave cuja primeira ocorrência está na linha `first_line`.
+    pub fn add(&mut self, first_line: usize, line_number: usize, content: &str) {
+        let group = self
+            .groups
+            .entry(first_line)
+            .or_insert_with(|| DuplicateGroup {
+                key: content.to_string(),
+                count: 1,
+                line_numbers: vec![first_line],
+            });
+        group.count += 1;
+        group.line_numbers.push(line_number);
+    }
+
+    /// Chaves com mais de uma ocorrência.
+    pub fn duplicates(&self) -> impl Iterator<Item = &DuplicateGroup> {
+        self.groups.values()
+    }
+
+    /// Grava o relatório em CSV (separador ';') ou JSON.
+    pub fn write(&self, path: &Path, format: ReportFormat) -> UniqueResult<()> {
+        let file_error = |e: std::io::Error| UniqueError::FileError {
+            path: path.display().to_string(),
+            source: e,
+        };
+
+        let mut file = create_file(path)?;
+
+        match format {
+            ReportFormat::Csv => {
+                let mut wtr = WriterBuilder::new().delimiter(b';').from_writer(file);
+                wtr.write_record(["key", "count", "line_numbers"])?;
+
+                for group in self.duplicates() {
+                    let line_numbers: Vec<String> =
+                        group.line_numbers.iter().map(|n| n.to_string()).collect();
+                    wtr.write_record([
+                        group.key.clone(),
+                        group.count.to_string(),
+                        line_numbers.join(","),
+                    ])?;
+                }
+                wtr.flush().map_err(file_error)?;
+            }
+            ReportFormat::Json => {
+                let groups: Vec<&DuplicateGroup> = self.duplicates().collect();
+                serde_json::to_writer_pretty(&mut file, &groups)
+                    .map_err(|e| file_error(e.into()))?;
+                writeln!(file).map_err(file_error)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// cargo test -- --show-output tests_report
+#[cfg(test)]
+mod tests_report {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_groups() {
+        // Linhas 2, 5 e 8 com a mesma chave; linhas 3 e 9 com outra
+        let mut report = DuplicateReport::new();
+        report.add(3, 9, "banana");
+        report.add(2, 5, "Apple");
+        report.add(2, 8, "APPLE");
+
+        let groups: Vec<&DuplicateGroup> = report.duplicates().collect();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].key, "Apple");
+        assert_eq!(groups[0].count, 3);
+        assert_eq!(groups[0].line_numbers, vec![2, 5, 8]);
+        assert_eq!(groups[1].line_numbers, vec![3, 9]);
+    }
+
+    #[test]
+    fn test_json_serialization() {
+        let group = DuplicateGroup {
+            key: "apple".to_string(),
+            count: 2,
+            line_numbers: vec![1, 4],
+        };
+        let json = serde_json::to_string(&group).unwrap();
+        assert_eq!(json, r#"{"key":"apple","count":2,"line_numbers":[1,4]}"#);
+    }
+}
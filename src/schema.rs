@@ -0,0 +1,479 @@
+use crate::{
+    format_number, null_values, parse_decimal, ExpectedType, NumberFormat, UniqueError,
+    UniqueResult, DATA_FORMAT,
+};
+use chrono::NaiveDate;
+use csv::StringRecord;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize, Serializer};
+use std::{fmt, path::Path};
+
+/// Tipo dos valores de uma coluna do esquema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+    /// Texto, mantido como está.
+    #[default]
+    String,
+    /// Número inteiro (i64); o separador de milhar '.' é ignorado.
+    Integer,
+    /// Número decimal (f64), conforme o `number_format` da coluna.
+    Float,
+    /// Data, conforme o `date_format` da coluna.
+    Date,
+    /// Número decimal exato (somas de `--sum`); não é aceito no arquivo de esquema.
+    #[serde(skip)]
+    Decimal,
+}
+
+impl From<ExpectedType> for ColumnType {
+    fn from(expected: ExpectedType) -> Self {
+        match expected {
+            ExpectedType::Date => ColumnType::Date,
+            ExpectedType::F64 => ColumnType::Float,
+            ExpectedType::I64 => ColumnType::Integer,
+        }
+    }
+}
+
+/// Descrição de uma coluna no arquivo de esquema.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnSpec {
+    /// Nome da coluna no cabeçalho (e na saída).
+    pub name: String,
+    /// Nomes alternativos aceitos no cabeçalho.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(default, rename = "type")]
+    pub column_type: ColumnType,
+    /// Valores tratados como nulos (substitui a lista do esquema).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub null_values: Option<Vec<String>>,
+    /// Formato das datas (padrão: "%d/%m/%Y").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_format: Option<String>,
+    /// Formato dos números decimais (padrão: o do esquema).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number_format: Option<NumberFormat>,
+    /// A coluna deve existir no cabeçalho.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+}
+
+/// Esquema declarativo das linhas (`--schema arquivo.toml`).
+///
+/// Example:
+/// ```toml
+/// null_values = ["", "N/A", "NULL"]
+/// number_format = "brazilian"
+///
+/// [[columns]]
+/// name = "CNPJ"
+/// aliases = ["CNPJ do Contribuinte"]
+/// required = true
+///
+/// [[columns]]
+/// name = "Valor Total"
+/// type = "float"
+///
+/// [[columns]]
+/// name = "Data de Emissão"
+/// type = "date"
+/// date_format = "%d/%m/%Y"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Schema {
+    /// Valores tratados como nulos em todas as colunas (padrão: `NULL_VALUES` ou `--null-values`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub null_values: Option<Vec<String>>,
+    /// Formato dos números decimais (padrão: brasileiro).
+    #[serde(default)]
+    pub number_format: NumberFormat,
+    pub columns: Vec<ColumnSpec>,
+}
+
+impl Schema {
+    /// Lê o esquema de um arquivo TOML.
+    pub fn load(path: &Path) -> UniqueResult<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| UniqueError::FileError {
+            path: path.display().to_string(),
+            source: e,
+        })?;
+        Self::parse(&text).map_err(|message| UniqueError::Schema {
+            path: path.display().to_string(),
+            message,
+        })
+    }
+
+    /// Interpreta o esquema a partir do texto TOML.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let schema: Schema = toml::from_str(text).map_err(|e| e.message().to_string())?;
+        if schema.columns.is_empty() {
+            return Err("nenhuma coluna declarada".to_string());
+        }
+        Ok(schema)
+    }
+
+    /// Associa as colunas do esquema às posições do cabeçalho.
+    ///
+    /// As colunas são procuradas pelo nome e, em seguida, pelos aliases.
+    /// Uma coluna obrigatória (`required`) ausente é um erro; as demais
+    /// ficam nulas.
+    pub fn bind(&self, header: &StringRecord) -> UniqueResult<SchemaBinding> {
+        let position = |name: &str| header.iter().position(|h| h.trim() == name.trim());
+
+        let columns = self
+            .columns
+            .iter()
+            .map(|spec| {
+                let index = std::iter::once(&spec.name)
+                    .chain(&spec.aliases)
+                    .find_map(|name| position(name));
+
+                if index.is_none() && spec.required {
+                    return Err(UniqueError::ColumnNotFound(spec.name.clone()));
+                }
+
+                let null_values = spec
+                    .null_values
+                    .clone()
+                    .or_else(|| self.null_values.clone())
+                    .unwrap_or_else(|| null_values().to_vec());
+
+                Ok(BoundColumn {
+                    name: spec.name.clone(),
+                    header: index.map(|i| header[i].to_string()),
+                    index,
+                    column_type: spec.column_type,
+                    null_values,
+                    date_format: spec
+                        .date_format
+                        .clone()
+                        .unwrap_or_else(|| DATA_FORMAT.to_string()),
+                    number_format: spec.number_format.unwrap_or(self.number_format),
+                })
+            })
+            .collect::<UniqueResult<Vec<BoundColumn>>>()?;
+
+        Ok(SchemaBinding { columns })
+    }
+}
+
+/// Valor tipado de uma célula.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum TypedValue {
+    #[default]
+    Null,
+    Text(String),
+    Integer(i64),
+    Float(f64),
+    Date(NaiveDate),
+    /// Decimal exato (somas de `--sum`).
+    Decimal(Decimal),
+}
+
+impl fmt::Display for TypedValue {
+    /// Datas no formato ISO (AAAA-MM-DD); veja `SchemaBinding::render`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypedValue::Null => Ok(()),
+            TypedValue::Text(s) => write!(f, "{s}"),
+            TypedValue::Integer(n) => write!(f, "{n}"),
+            TypedValue::Float(n) => write!(f, "{n}"),
+            TypedValue::Date(d) => write!(f, "{}", d.format("%Y-%m-%d")),
+            TypedValue::Decimal(n) => write!(f, "{n}"),
+        }
+    }
+}
+
+impl Serialize for TypedValue {
+    /// Números como números (decimais com todos os dígitos), datas no
+    /// formato ISO e nulos como `null` (usado por `--output-format ndjson|json`).
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            TypedValue::Null => serializer.serialize_none(),
+            TypedValue::Text(s) => serializer.serialize_str(s),
+            TypedValue::Integer(n) => serializer.serialize_i64(*n),
+            TypedValue::Float(n) => serializer.serialize_f64(*n),
+            TypedValue::Date(_) => serializer.collect_str(self),
+            TypedValue::Decimal(n) => {
+                rust_decimal::serde::arbitrary_precision::serialize(n, serializer)
+            }
+        }
+    }
+}
+
+/// Coluna do esquema associada à sua posição no cabeçalho.
+#[derive(Debug, Clone)]
+struct BoundColumn {
+    name: String,
+    /// Nome encontrado no cabeçalho (o próprio nome ou um alias).
+    header: Option<String>,
+    index: Option<usize>,
+    column_type: ColumnType,
+    null_values: Vec<String>,
+    date_format: String,
+    number_format: NumberFormat,
+}
+
+impl BoundColumn {
+    fn is_null(&self, value: &str) -> bool {
+        let value = value.trim();
+        self.null_values
+            .iter()
+            .any(|null| null.trim().eq_ignore_ascii_case(value))
+    }
+
+    /// Converte o valor bruto para o tipo da coluna.
+    ///
+    /// Em caso de falha, retorna o tipo esperado.
+    fn parse(&self, value: &str) -> Result<TypedValue, ExpectedType> {
+        if self.is_null(value) {
+            return Ok(TypedValue::Null);
+        }
+
+        let value = value.trim();
+        match self.column_type {
+            ColumnType::String => Ok(TypedValue::Text(value.to_string())),
+            ColumnType::Integer => parse_integer(value, self.number_format)
+                .map(TypedValue::Integer)
+                .ok_or(ExpectedType::I64),
+            ColumnType::Float => format_number(value, self.number_format)
+                .parse()
+                .map(TypedValue::Float)
+                .map_err(|_| ExpectedType::F64),
+            ColumnType::Date => NaiveDate::parse_from_str(value, &self.date_format)
+                .map(TypedValue::Date)
+                .map_err(|_| ExpectedType::Date),
+            ColumnType::Decimal => parse_decimal(value, self.number_format)
+                .map(TypedValue::Decimal)
+                .ok_or(ExpectedType::F64),
+        }
+    }
+}
+
+/// Converte um inteiro no formato numérico da coluna.
+///
+/// O separador de milhar ('.' no formato brasileiro, ',' no internacional)
+/// só é aceito entre grupos de três dígitos; valores com parte decimal
+/// são rejeitados.
+fn parse_integer(value: &str, number_format: NumberFormat) -> Option<i64> {
+    let thousands = match number_format {
+        NumberFormat::Brazilian => '.',
+        NumberFormat::International => ',',
+    };
+
+    let digits = value.trim_start_matches(['-', '+']);
+    let mut groups = digits.split(thousands);
+    let first = groups.next().unwrap_or_default();
+    let grouped = digits.contains(thousands);
+
+    if grouped && (!(1..=3).contains(&first.len()) || groups.any(|group| group.len() != 3)) {
+        return None;
+    }
+
+    value.replace(thousands, "").parse().ok()
+}
+
+/// Esquema associado ao cabeçalho de um arquivo: o motor de linhas tipadas.
+#[derive(Debug, Clone)]
+pub struct SchemaBinding {
+    columns: Vec<BoundColumn>,
+}
+
+impl SchemaBinding {
+    /// Nomes das colunas da saída, na ordem do esquema.
+    pub fn headers(&self) -> Vec<String> {
+        self.columns.iter().map(|c| c.name.clone()).collect()
+    }
+
+    /// Tipos das colunas da saída, na ordem do esquema.
+    pub fn column_types(&self) -> Vec<ColumnType> {
+        self.columns.iter().map(|c| c.column_type).collect()
+    }
+
+    /// Valida e converte uma linha, na ordem das colunas do esquema.
+    ///
+    /// Colunas ausentes do cabeçalho ou da linha resultam em `TypedValue::Null`.
+    pub fn parse_row(
+        &self,
+        line_number: usize,
+        record: &StringRecord,
+    ) -> UniqueResult<Vec<TypedValue>> {
+        self.columns
+            .iter()
+            .map(|column| {
+                let Some(value) = column.index.and_then(|i| record.get(i)) else {
+                    return Ok(TypedValue::Null);
+                };
+
+                column
+                    .parse(value)
+                    .map_err(|expected| UniqueError::FieldMapping {
+                        line_number,
+                        column: column.header.clone().unwrap_or_else(|| column.name.clone()),
+                        value: value.to_string(),
+                        expected,
+                    })
+            })
+            .collect()
+    }
+
+    /// Converte os valores tipados em texto para a saída CSV.
+    ///
+    /// As datas são escritas no `date_format` da coluna.
+    pub fn render(&self, row: &[TypedValue]) -> Vec<String> {
+        row.iter()
+            .zip(&self.columns)
+            .map(|(value, column)| match value {
+                TypedValue::Date(d) => d.format(&column.date_format).to_string(),
+                other => other.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// cargo test -- --show-output tests_schema
+#[cfg(test)]
+mod tests_schema {
+    use super::*;
+
+    const SCHEMA: &str = r#"
+        [[columns]]
+        name = "CNPJ"
+        aliases = ["CNPJ do Contribuinte"]
+        required = true
+
+        [[columns]]
+        name = "Valor"
+        type = "float"
+
+        [[columns]]
+        name = "Quantidade"
+        type = "integer"
+        null_values = ["", "-"]
+
+        [[columns]]
+        name = "Emissão"
+        type = "date"
+
+        [[columns]]
+        name = "Observação"
+    "#;
+
+    fn binding() -> SchemaBinding {
+        let header = StringRecord::from(vec![
+            "Emissão",
+            "CNPJ do Contribuinte",
+            "Valor",
+            "Quantidade",
+        ]);
+        Schema::parse(SCHEMA).unwrap().bind(&header).unwrap()
+    }
+
+    #[test]
+    fn test_parse_typed_row() {
+        let binding = binding();
+        assert_eq!(
+            binding.headers(),
+            vec!["CNPJ", "Valor", "Quantidade", "Emissão", "Observação"]
+        );
+
+        let record = StringRecord::from(vec![
+            "31/01/2024",
+            "12.345.678/0001-99",
+            "1.234,50",
+            "1.000",
+        ]);
+        let row = binding.parse_row(2, &record).unwrap();
+        assert_eq!(
+            row,
+            vec![
+                TypedValue::Text("12.345.678/0001-99".to_string()),
+                TypedValue::Float(1234.5),
+                TypedValue::Integer(1000),
+                TypedValue::Date(NaiveDate::from_ymd_opt(2024, 1, 31).unwrap()),
+                TypedValue::Null,
+            ]
+        );
+        assert_eq!(
+            binding.render(&row),
+            vec!["12.345.678/0001-99", "1234.5", "1000", "31/01/2024", ""]
+        );
+        assert_eq!(
+            serde_json::to_string(&row).unwrap(),
+            r#"["12.345.678/0001-99",1234.5,1000,"2024-01-31",null]"#
+        );
+    }
+
+    #[test]
+    fn test_null_values_and_errors() {
+        let binding = binding();
+
+        let record = StringRecord::from(vec!["", "1", "N/A", "-"]);
+        let row = binding.parse_row(3, &record).unwrap();
+        assert_eq!(row[1], TypedValue::Null);
+        assert_eq!(row[2], TypedValue::Null);
+
+        // "N/A" não é nulo na coluna Quantidade (lista própria)
+        let record = StringRecord::from(vec!["", "1", "", "N/A"]);
+        match binding.parse_row(4, &record) {
+            Err(UniqueError::FieldMapping {
+                line_number,
+                column,
+                value,
+                expected,
+            }) => {
+                assert_eq!(line_number, 4);
+                assert_eq!(column, "Quantidade");
+                assert_eq!(value, "N/A");
+                assert_eq!(expected, ExpectedType::I64);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_integer_thousands_separators() {
+        let parse = |value: &str, number_format: NumberFormat| {
+            let column = BoundColumn {
+                name: "Quantidade".to_string(),
+                header: None,
+                index: None,
+                column_type: ColumnType::Integer,
+                null_values: Vec::new(),
+                date_format: DATA_FORMAT.to_string(),
+                number_format,
+            };
+            column.parse(value)
+        };
+
+        let br = NumberFormat::Brazilian;
+        assert_eq!(parse("1.000", br), Ok(TypedValue::Integer(1000)));
+        assert_eq!(parse("-1.234.567", br), Ok(TypedValue::Integer(-1234567)));
+        assert_eq!(parse("1.5", br), Err(ExpectedType::I64));
+        assert_eq!(parse("1,5", br), Err(ExpectedType::I64));
+        assert_eq!(parse("1,000", br), Err(ExpectedType::I64));
+
+        let intl = NumberFormat::International;
+        assert_eq!(parse("1,000", intl), Ok(TypedValue::Integer(1000)));
+        assert_eq!(parse("1.000", intl), Err(ExpectedType::I64));
+        assert_eq!(parse("1.5", intl), Err(ExpectedType::I64));
+        assert_eq!(parse("1,5", intl), Err(ExpectedType::I64));
+        assert_eq!(parse("12,34,567", intl), Err(ExpectedType::I64));
+    }
+
+    #[test]
+    fn test_required_column_and_invalid_schema() {
+        let header = StringRecord::from(vec!["Valor"]);
+        assert!(Schema::parse(SCHEMA).unwrap().bind(&header).is_err());
+
+        assert!(Schema::parse("columns = []").is_err());
+        assert!(Schema::parse("[[columns]]\nname = \"A\"\ntype = \"money\"").is_err());
+    }
+}
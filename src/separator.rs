@@ -0,0 +1,152 @@
+use csv::ReaderBuilder;
+use std::{fmt, str::FromStr};
+
+/// Separador padrão das colunas de arquivos CSV.
+pub const DEFAULT_SEPARATOR: char = ';';
+
+/// Separadores testados pela detecção automática, em ordem de preferência.
+const CANDIDATES: [char; 4] = [';', ',', '|', '\t'];
+
+/// Separador (delimitador) de colunas informado em `--separator`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Separator {
+    /// Detectado a partir do cabeçalho e das primeiras linhas.
+    Auto,
+    Char(char),
+}
+
+impl Separator {
+    /// O caractere separador (`DEFAULT_SEPARATOR` enquanto não detectado).
+    pub fn char(self) -> char {
+        match self {
+            Separator::Auto => DEFAULT_SEPARATOR,
+            Separator::Char(c) => c,
+        }
+    }
+
+    /// O separador como byte, para o leitor de CSV.
+    pub fn byte(self) -> u8 {
+        self.char() as u8
+    }
+}
+
+impl Default for Separator {
+    fn default() -> Self {
+        Separator::Char(DEFAULT_SEPARATOR)
+    }
+}
+
+impl fmt::Display for Separator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Separator::Auto => write!(f, "auto"),
+            Separator::Char(c) => write!(f, "{c}"),
+        }
+    }
+}
+
+impl FromStr for Separator {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.eq_ignore_ascii_case("auto") {
+            return Ok(Separator::Auto);
+        }
+
+        let mut chars = value.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii() => Ok(Separator::Char(c)),
+            _ => Err(format!(
+                "'{value}' must be a single ASCII character or 'auto'"
+            )),
+        }
+    }
+}
+
+/// Valida o separador da saída CSV (`--output-separator`): um único caractere ASCII.
+pub fn parse_output_separator(value: &str) -> Result<char, String> {
+    match value.parse() {
+        Ok(Separator::Char(c)) => Ok(c),
+        _ => Err(format!("'{value}' must be a single ASCII character")),
+    }
+}
+
+/// Detecta o separador de colunas a partir de uma amostra do arquivo
+/// (cabeçalho seguido das primeiras linhas).
+///
+/// Cada candidato é pontuado pelo número de linhas com a mesma quantidade
+/// de colunas do cabeçalho e, em caso de empate, pelo número de colunas.
+/// Candidatos que produzem uma única coluna são descartados.
+///
+/// Example:
+/// ```
+/// use unique::detect_separator;
+/// let sample = "nome,valor\nana,1\nbia,2\n";
+/// assert_eq!(detect_separator(sample.as_bytes()), Some(','));
+/// ```
+pub fn detect_separator(sample: &[u8]) -> Option<char> {
+    // Em caso de empate, prevalece o primeiro candidato
+    CANDIDATES
+        .iter()
+        .rev()
+        .filter_map(|&candidate| score(sample, candidate).map(|score| (candidate, score)))
+        .max_by(|(_, a), (_, b)| a.cmp(b))
+        .map(|(candidate, _)| candidate)
+}
+
+/// Pontuação do candidato: (linhas consistentes com o cabeçalho, número de colunas).
+fn score(sample: &[u8], candidate: char) -> Option<(usize, usize)> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(candidate as u8)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(sample);
+
+    let mut records = reader.records().map_while(Result::ok);
+
+    let num_cols = records.next()?.len();
+    if num_cols < 2 {
+        return None;
+    }
+
+    let consistent = records.filter(|record| record.len() == num_cols).count();
+    Some((consistent, num_cols))
+}
+
+/// cargo test -- --show-output tests_separator
+#[cfg(test)]
+mod tests_separator {
+    use super::*;
+
+    #[test]
+    fn test_parse_separator() {
+        assert_eq!("auto".parse(), Ok(Separator::Auto));
+        assert_eq!("|".parse(), Ok(Separator::Char('|')));
+        assert!("ab".parse::<Separator>().is_err());
+        assert_eq!(Separator::Auto.char(), ';');
+        assert_eq!(Separator::Char(',').to_string(), ",");
+
+        assert_eq!(parse_output_separator("|"), Ok('|'));
+        assert!(parse_output_separator("ç").is_err());
+        assert!(parse_output_separator("auto").is_err());
+    }
+
+    #[test]
+    fn test_detect_by_consistency() {
+        // A vírgula decimal não deve confundir a detecção
+        let sample = "CNPJ;Nome;Valor\n1;Ana;1,50\n2;Bia;2,75\n3;Caio;10,00\n";
+        assert_eq!(detect_separator(sample.as_bytes()), Some(';'));
+
+        let sample = "a|b|c|d\n1|2|3|4\n5|6|7|8\n";
+        assert_eq!(detect_separator(sample.as_bytes()), Some('|'));
+
+        let sample = "a\tb\n\"x, y\"\t2\n";
+        assert_eq!(detect_separator(sample.as_bytes()), Some('\t'));
+    }
+
+    #[test]
+    fn test_single_column_is_not_detected() {
+        assert_eq!(detect_separator(b"nome\nana\nbia\n"), None);
+        assert_eq!(detect_separator(b""), None);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{NumberFormat, UniqueError, UniqueResult, DATA_FORMAT};
+use calamine::{open_workbook_auto, Data, DataType, Reader};
+use csv::WriterBuilder;
+use std::path::Path;
+
+/// Extensões dos arquivos lidos como planilhas.
+const EXTENSIONS: [&str; 5] = ["xlsx", "xlsm", "xlsb", "xls", "ods"];
+
+/// Indica se o arquivo é uma planilha (pela extensão).
+///
+/// Example:
+/// ```
+/// use std::path::Path;
+/// use unique::is_spreadsheet;
+/// assert!(is_spreadsheet(Path::new("relatorio.XLSX")));
+/// assert!(is_spreadsheet(Path::new("dados.ods")));
+/// assert!(!is_spreadsheet(Path::new("dados.csv")));
+/// ```
+pub fn is_spreadsheet(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+}
+
+/// Lê uma planilha e a converte em texto CSV, com o separador informado,
+/// para alimentar o mesmo processamento dos arquivos CSV.
+///
+/// A planilha é escolhida pelo nome ou pelo índice (a partir de 1);
+/// sem `sheet`, é lida a primeira. As células são convertidas por `cell_to_string`.
+pub fn read_spreadsheet(
+    path: &Path,
+    sheet: Option<&str>,
+    separator: char,
+    number_format: NumberFormat,
+) -> UniqueResult<Vec<u8>> {
+    let error = |message: String| UniqueError::Spreadsheet {
+        path: path.display().to_string(),
+        message,
+    };
+
+    let mut workbook = open_workbook_auto(path).map_err(|e| error(e.to_string()))?;
+    let names = workbook.sheet_names();
+
+    let name = match sheet {
+        None => names.first(),
+        Some(sheet) => names.iter().find(|name| *name == sheet).or_else(|| {
+            sheet
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| index.checked_sub(1))
+                .and_then(|index| names.get(index))
+        }),
+    }
+    .cloned()
+    .ok_or_else(|| {
+        error(format!(
+            "planilha '{}' não encontrada (disponíveis: {})",
+            sheet.unwrap_or_default(),
+            names.join(", ")
+        ))
+    })?;
+
+    let range = workbook
+        .worksheet_range(&name)
+        .map_err(|e| error(e.to_string()))?;
+
+    let mut writer = WriterBuilder::new()
+        .delimiter(separator as u8)
+        .flexible(true)
+        .from_writer(vec![]);
+
+    for row in range.rows() {
+        writer.write_record(row.iter().map(|cell| cell_to_string(cell, number_format)))?;
+    }
+
+    Ok(writer.into_inner().map_err(|e| e.into_error())?)
+}
+
+/// Converte uma célula em texto, como nos arquivos CSV exportados:
+///
+/// - números no formato de `--number-format` (sem separador de milhar),
+///   de modo que `format_number` e `-m` os interpretem corretamente;
+/// - datas no formato DD/MM/AAAA (ver `format_date`), com a hora, se houver;
+/// - quebras de linha dentro do texto substituídas por espaço, pois cada
+///   linha do CSV é lida como um registro.
+///
+/// Example:
+/// ```
+/// use calamine::Data;
+/// use unique::{cell_to_string, NumberFormat};
+/// assert_eq!(cell_to_string(&Data::Float(1250.5), NumberFormat::Brazilian), "1250,5");
+/// assert_eq!(cell_to_string(&Data::Float(1250.5), NumberFormat::International), "1250.5");
+/// assert_eq!(cell_to_string(&Data::Float(5102.0), NumberFormat::Brazilian), "5102");
+/// assert_eq!(cell_to_string(&Data::Empty, NumberFormat::Brazilian), "");
+/// let text = Data::String("Rua A,\r\nnº 10\nCentro".to_string());
+/// assert_eq!(cell_to_string(&text, NumberFormat::Brazilian), "Rua A, nº 10 Centro");
+/// ```
+pub fn cell_to_string(cell: &Data, number_format: NumberFormat) -> String {
+    match cell {
+        Data::Int(n) => n.to_string(),
+        Data::Float(n) if n.fract() == 0.0 && n.abs() < 1e15 => (*n as i64).to_string(),
+        Data::Float(n) => match number_format {
+            NumberFormat::Brazilian => n.to_string().replace('.', ","),
+            NumberFormat::International => n.to_string(),
+        },
+        Data::String(s) | Data::DurationIso(s) => s.replace("\r\n", " ").replace(['\n', '\r'], " "),
+        Data::Bool(b) => b.to_string(),
+        Data::DateTime(_) | Data::DateTimeIso(_) => match cell.as_datetime() {
+            Some(datetime) if datetime.time() == chrono::NaiveTime::MIN => {
+                datetime.format(DATA_FORMAT).to_string()
+            }
+            Some(datetime) => datetime.format("%d/%m/%Y %H:%M:%S").to_string(),
+            None => cell.to_string(),
+        },
+        Data::Error(e) => e.to_string(),
+        Data::Empty => String::new(),
+    }
+}
+
+/// cargo test -- --show-output tests_spreadsheet
+#[cfg(test)]
+mod tests_spreadsheet {
+    use super::*;
+    use crate::Arguments;
+    use chrono::NaiveDate;
+    use rust_xlsxwriter::{Format, Workbook};
+
+    #[test]
+    fn test_read_sheet_by_name_and_index() {
+        let path =
+            std::env::temp_dir().join(format!("tests_spreadsheet_{}.xlsx", std::process::id()));
+
+        let mut workbook = Workbook::new();
+        workbook.add_worksheet().set_name("Resumo").unwrap();
+        let worksheet = workbook.add_worksheet().set_name("Itens").unwrap();
+        let date = NaiveDate::from_ymd_opt(2023, 5, 1).unwrap();
+        let date_format = Format::new().set_num_format("dd/mm/yyyy");
+        worksheet
+            .write_row(0, 0, ["Chave", "Emissão", "Valor", "CFOP", "Obs"])
+            .unwrap();
+        worksheet
+            .write_string(1, 0, "35230100000000000000000000000000000000000001")
+            .unwrap();
+        worksheet
+            .write_datetime_with_format(1, 1, date, &date_format)
+            .unwrap();
+        worksheet.write_number(1, 2, 1250.5).unwrap();
+        worksheet.write_number(1, 3, 5102).unwrap();
+        worksheet.write_string(1, 4, "Linha 1\nLinha 2").unwrap();
+        workbook.save(&path).unwrap();
+
+        let expected = "Chave;Emissão;Valor;CFOP;Obs\n\
+                        35230100000000000000000000000000000000000001;01/05/2023;1250,5;5102;Linha 1 Linha 2\n";
+
+        for sheet in ["Itens", "2"] {
+            let csv = read_spreadsheet(&path, Some(sheet), ';', NumberFormat::Brazilian).unwrap();
+            assert_eq!(String::from_utf8(csv).unwrap(), expected);
+        }
+
+        assert!(read_spreadsheet(&path, Some("3"), ';', NumberFormat::Brazilian).is_err());
+
+        // A planilha implica --csv: as opções que o exigem são aceitas
+        let arguments = Arguments::try_parse_args_from([
+            "unique".as_ref(),
+            path.as_os_str(),
+            "--sheet".as_ref(),
+            "Itens".as_ref(),
+            "--output-format".as_ref(),
+            "ndjson".as_ref(),
+            "-f".as_ref(),
+        ])
+        .unwrap();
+        assert!(arguments.parse_csv_file);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,469 @@
+use crate::{write_file_or_stderr, Arguments, LineError, StatsFormat, UniqueResult};
+use execution_time::ExecutionTime;
+use serde::Serialize;
+use std::{collections::HashSet, io::Write};
+
+/// Número máximo de caracteres da linha exibidos em um exemplo.
+const SNIPPET_LEN: usize = 80;
+
+/// Linha cujo número de colunas difere do cabeçalho.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ColumnMismatch {
+    pub line_number: usize,
+    pub column_count: usize,
+    /// Início da linha original.
+    pub snippet: String,
+}
+
+/// Linhas que quebram a consistência de colunas do arquivo CSV,
+/// com até `max_examples` exemplos.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ColumnMismatches {
+    /// Número de colunas do cabeçalho.
+    pub expected: usize,
+    /// Total de linhas com número de colunas diferente do cabeçalho.
+    pub total: usize,
+    /// Linhas gravadas no `--reject-file` (não processadas).
+    pub rejected: usize,
+    pub examples: Vec<ColumnMismatch>,
+    #[serde(skip)]
+    max_examples: usize,
+}
+
+impl ColumnMismatches {
+    pub fn new(expected: usize, max_examples: usize) -> Self {
+        Self {
+            expected,
+            max_examples,
+            ..Default::default()
+        }
+    }
+
+    /// Registra uma linha inconsistente, guardando o exemplo se o limite permitir.
+    pub fn add(&mut self, line_number: usize, column_count: usize, original: &str) {
+        self.total += 1;
+
+        if self.examples.len() < self.max_examples {
+            let mut snippet: String = original.chars().take(SNIPPET_LEN).collect();
+            if original.chars().nth(SNIPPET_LEN).is_some() {
+                snippet.push('…');
+            }
+            self.examples.push(ColumnMismatch {
+                line_number,
+                column_count,
+                snippet,
+            });
+        }
+    }
+}
+
+/// Linhas que não puderam ser processadas (`--on-error skip|keep-raw`),
+/// com até `max_examples` exemplos.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LineErrors {
+    /// Total de linhas com erro.
+    pub total: usize,
+    pub examples: Vec<LineError>,
+    #[serde(skip)]
+    max_examples: usize,
+}
+
+impl LineErrors {
+    pub fn new(max_examples: usize) -> Self {
+        Self {
+            max_examples,
+            ..Default::default()
+        }
+    }
+
+    /// Registra o erro de uma linha, guardando o exemplo se o limite permitir.
+    pub fn add(&mut self, error: &LineError) {
+        self.total += 1;
+
+        if self.examples.len() < self.max_examples {
+            self.examples.push(error.clone());
+        }
+    }
+}
+
+/// Estrutura das colunas observada em um arquivo CSV.
+#[derive(Debug, Clone, Serialize)]
+pub struct CsvStats {
+    /// Separador (delimitador) de colunas.
+    pub separator: char,
+    /// Indica se o separador foi detectado automaticamente (`--separator auto`).
+    pub separator_detected: bool,
+    /// Indica se todas as linhas possuem o mesmo número (não nulo) de colunas.
+    pub valid: bool,
+    /// Números de colunas observados nas linhas, em ordem crescente.
+    pub column_counts: Vec<usize>,
+    /// Linhas cujo número de colunas difere do cabeçalho.
+    pub mismatches: ColumnMismatches,
+}
+
+impl CsvStats {
+    pub fn new(
+        separator: char,
+        separator_detected: bool,
+        delimiter_set: HashSet<usize>,
+        mismatches: ColumnMismatches,
+    ) -> Self {
+        // Coleta do HashSet para Vector
+        let mut column_counts = delimiter_set.into_iter().collect::<Vec<usize>>();
+        column_counts.sort();
+
+        // Mais de um número de colunas (inconsistência) ou zero colunas invalidam o arquivo
+        let valid = column_counts.len() == 1 && !column_counts.contains(&0);
+
+        Self {
+            separator,
+            separator_detected,
+            valid,
+            column_counts,
+            mismatches,
+        }
+    }
+}
+
+/// Métricas da execução exibidas por `--verbose` ou gravadas em `--stats-file`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Statistics {
+    pub total_lines: usize,
+    pub empty_lines: usize,
+    pub unique_lines: usize,
+    pub repeated_lines: usize,
+    /// Taxa de deduplicação (%).
+    pub deduplication_rate: f64,
+    pub output_lines: usize,
+    pub runtime_seconds: f64,
+    pub runtime: String,
+    pub throughput_lines_per_sec: usize,
+    pub csv: Option<CsvStats>,
+    /// Chaves de NF-e com dígito verificador inválido (apenas com `--check`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invalid_nfe_keys: Option<usize>,
+    /// Linhas que não puderam ser processadas (apenas com `--on-error skip|keep-raw`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_errors: Option<LineErrors>,
+}
+
+/// Códigos de saída do modo `--check`.
+///
+/// Quando mais de um problema é encontrado, prevalece o de maior código.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// Nenhum problema encontrado.
+    Clean = 0,
+    /// Linhas repetidas encontradas.
+    Duplicates = 2,
+    /// Número de colunas inconsistente no arquivo CSV.
+    InvalidCsv = 3,
+    /// Chaves de NF-e com dígito verificador inválido.
+    InvalidNfeKeys = 4,
+}
+
+impl CheckStatus {
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+impl Statistics {
+    pub fn new(
+        args: &Arguments,
+        timer: &ExecutionTime,
+        num_unique_lines: usize,
+        num_repeated_lines: usize,
+        num_empty_lines: usize,
+        csv: Option<CsvStats>,
+        invalid_nfe_keys: Option<usize>,
+    ) -> Self {
+        let duration = timer.get_duration();
+        let num_total_lines_original = num_unique_lines + num_repeated_lines;
+
+        // Cálculo da taxa de redução (deduplicação)
+        let reduction_percent = if num_total_lines_original > 0 {
+            (num_repeated_lines as f64 / num_total_lines_original as f64) * 100.0
+        } else {
+            0.0
+        };
+
+        // Cálculo de vazão (throughput)
+        let lines_per_sec = if duration.as_secs_f64() > 0.0 {
+            (num_total_lines_original as f64 / duration.as_secs_f64()) as usize
+        } else {
+            0
+        };
+
+        // Linhas finais no arquivo de saída
+        let num_total_lines_final = if args.remove_empty_lines && num_empty_lines > 0 {
+            num_unique_lines.saturating_sub(1)
+        } else {
+            num_unique_lines
+        };
+
+        Self {
+            total_lines: num_total_lines_original,
+            empty_lines: num_empty_lines,
+            unique_lines: num_unique_lines,
+            repeated_lines: num_repeated_lines,
+            deduplication_rate: reduction_percent,
+            output_lines: num_total_lines_final,
+            runtime_seconds: duration.as_secs_f64(),
+            runtime: timer.get_elapsed_time(),
+            throughput_lines_per_sec: lines_per_sec,
+            csv,
+            invalid_nfe_keys,
+            line_errors: None,
+        }
+    }
+
+    /// Resultado da validação do modo `--check`.
+    pub fn check_status(&self) -> CheckStatus {
+        if self.invalid_nfe_keys.is_some_and(|n| n > 0) {
+            CheckStatus::InvalidNfeKeys
+        } else if self.csv.as_ref().is_some_and(|csv| !csv.valid) {
+            CheckStatus::InvalidCsv
+        } else if self.repeated_lines > 0 {
+            CheckStatus::Duplicates
+        } else {
+            CheckStatus::Clean
+        }
+    }
+
+    /// Escreve o resumo da execução em texto (formato de `--verbose`).
+    pub fn write_text<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        let max_len = self.total_lines.to_string().len().max(10);
+
+        writeln!(w, "\n{}", "=".repeat(45))?;
+        writeln!(w, "📊 EXECUTIONS SUMMARY")?;
+        writeln!(w, "{}", "=".repeat(45))?;
+
+        writeln!(w, "📝 INPUT STATS:")?;
+        writeln!(
+            w,
+            "   • Total lines processed : {:>max_len$}",
+            self.total_lines
+        )?;
+        writeln!(
+            w,
+            "   • Total empty lines     : {:>max_len$}",
+            self.empty_lines
+        )?;
+
+        writeln!(w, "\n🔍 PROCESSING DETAILS:")?;
+        writeln!(
+            w,
+            "   • Unique lines found    : {:>max_len$}",
+            self.unique_lines
+        )?;
+        writeln!(
+            w,
+            "   • Repeated lines removed: {:>max_len$}",
+            self.repeated_lines
+        )?;
+        writeln!(
+            w,
+            "   • Deduplication rate    : {:>max_len$.2}%",
+            self.deduplication_rate
+        )?;
+
+        writeln!(w, "\n💾 OUTPUT STATS:")?;
+        writeln!(
+            w,
+            "   • Lines in final file   : {:>max_len$}",
+            self.output_lines
+        )?;
+
+        writeln!(w, "\n⏱️  PERFORMANCE:")?;
+        writeln!(w, "   • Total run time        : {}", self.runtime)?;
+        writeln!(
+            w,
+            "   • Throughput            : {} lines/sec",
+            self.throughput_lines_per_sec
+        )?;
+        writeln!(w, "{}\n", "=".repeat(45))
+    }
+
+    /// Emite as estatísticas com `--verbose` ou `--stats-file`.
+    ///
+    /// O destino é o arquivo `--stats-file` (ou a saída de erro padrão)
+    /// e o formato é definido por `--stats-format`.
+    pub fn emit(&self, args: &Arguments) -> UniqueResult<()> {
+        if !args.verbose && args.stats_file.is_none() {
+            return Ok(());
+        }
+
+        let mut writer = write_file_or_stderr(args.stats_file.as_deref())?;
+
+        match args.stats_format {
+            StatsFormat::Text => self.write_text(&mut writer)?,
+            StatsFormat::Json => {
+                serde_json::to_writer_pretty(&mut writer, self).map_err(std::io::Error::from)?;
+                writeln!(writer)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// cargo test -- --show-output tests_stats
+#[cfg(test)]
+mod tests_stats {
+    use super::*;
+    use crate::{ExpectedType, UniqueError};
+
+    #[test]
+    fn test_csv_stats_validity() {
+        assert!(CsvStats::new(';', false, HashSet::from([3]), ColumnMismatches::default()).valid);
+        assert!(
+            !CsvStats::new(
+                ';',
+                false,
+                HashSet::from([3, 4]),
+                ColumnMismatches::default()
+            )
+            .valid
+        );
+        assert!(!CsvStats::new(';', false, HashSet::from([0]), ColumnMismatches::default()).valid);
+
+        let stats = CsvStats::new(
+            ',',
+            false,
+            HashSet::from([4, 3]),
+            ColumnMismatches::default(),
+        );
+        assert_eq!(stats.column_counts, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_column_mismatch_examples() {
+        let mut mismatches = ColumnMismatches::new(3, 2);
+        mismatches.add(5, 4, "1;2;3;4");
+        mismatches.add(9, 2, &"x".repeat(100));
+        mismatches.add(12, 1, "1");
+
+        assert_eq!(mismatches.total, 3);
+        assert_eq!(mismatches.examples.len(), 2);
+        assert_eq!(
+            mismatches.examples[0],
+            ColumnMismatch {
+                line_number: 5,
+                column_count: 4,
+                snippet: "1;2;3;4".to_string(),
+            }
+        );
+        assert_eq!(mismatches.examples[1].snippet.chars().count(), 81);
+        assert!(mismatches.examples[1].snippet.ends_with('…'));
+    }
+
+    #[test]
+    fn test_line_error_examples() {
+        let error = UniqueError::FieldMapping {
+            line_number: 7,
+            column: "Valor Total".to_string(),
+            value: "12,3x".to_string(),
+            expected: ExpectedType::F64,
+        };
+
+        let mut errors = LineErrors::new(1);
+        errors.add(&LineError::new(7, &error));
+        errors.add(&LineError::new(9, &UniqueError::Unknown("x".to_string())));
+
+        assert_eq!(errors.total, 2);
+        assert_eq!(errors.examples.len(), 1);
+        assert_eq!(errors.examples[0].line_number, 7);
+        assert_eq!(errors.examples[0].column.as_deref(), Some("Valor Total"));
+        assert_eq!(errors.examples[0].value.as_deref(), Some("12,3x"));
+        assert_eq!(errors.examples[0].expected, Some(ExpectedType::F64));
+    }
+
+    #[test]
+    fn test_stats_format_requires_output() {
+        use clap::Parser;
+
+        let parse = |options: &[&str]| {
+            Arguments::try_parse_from(std::iter::once("unique").chain(options.iter().copied()))
+        };
+
+        assert!(parse(&["--stats-format", "json"]).is_err());
+        assert!(parse(&["--stats-format", "json", "-v"]).is_ok());
+        assert!(parse(&["--stats-format", "json", "--stats-file", "stats.json"]).is_ok());
+        assert!(parse(&["-v", "--stats-file", "stats.json"]).is_ok());
+        assert!(parse(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_statistics_json() {
+        let stats = Statistics {
+            total_lines: 4,
+            empty_lines: 1,
+            unique_lines: 3,
+            repeated_lines: 1,
+            deduplication_rate: 25.0,
+            output_lines: 3,
+            runtime_seconds: 0.5,
+            runtime: "0.5 second".to_string(),
+            throughput_lines_per_sec: 8,
+            csv: Some(CsvStats::new(
+                ';',
+                false,
+                HashSet::from([2]),
+                ColumnMismatches::default(),
+            )),
+            invalid_nfe_keys: None,
+            line_errors: None,
+        };
+
+        let json: serde_json::Value = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["total_lines"], 4);
+        assert_eq!(json["deduplication_rate"], 25.0);
+        assert_eq!(json["csv"]["separator"], ";");
+        assert_eq!(json["csv"]["valid"], true);
+        assert_eq!(json["csv"]["column_counts"], serde_json::json!([2]));
+        assert!(json.get("invalid_nfe_keys").is_none());
+
+        // Linhas repetidas
+        assert_eq!(stats.check_status(), CheckStatus::Duplicates);
+    }
+
+    #[test]
+    fn test_check_status_priority() {
+        let mut stats = Statistics {
+            total_lines: 2,
+            empty_lines: 0,
+            unique_lines: 2,
+            repeated_lines: 0,
+            deduplication_rate: 0.0,
+            output_lines: 2,
+            runtime_seconds: 0.0,
+            runtime: String::new(),
+            throughput_lines_per_sec: 0,
+            csv: Some(CsvStats::new(
+                ';',
+                false,
+                HashSet::from([2]),
+                ColumnMismatches::default(),
+            )),
+            invalid_nfe_keys: Some(0),
+            line_errors: None,
+        };
+        assert_eq!(stats.check_status(), CheckStatus::Clean);
+        assert_eq!(stats.check_status().code(), 0);
+
+        stats.repeated_lines = 1;
+        assert_eq!(stats.check_status().code(), 2);
+
+        stats.csv = Some(CsvStats::new(
+            ';',
+            false,
+            HashSet::from([2, 3]),
+            ColumnMismatches::default(),
+        ));
+        assert_eq!(stats.check_status().code(), 3);
+
+        stats.invalid_nfe_keys = Some(1);
+        assert_eq!(stats.check_status().code(), 4);
+    }
+}
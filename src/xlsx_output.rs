@@ -0,0 +1,133 @@
+use crate::{TypedValue, UniqueResult};
+use rust_decimal::prelude::ToPrimitive;
+use rust_xlsxwriter::{ColNum, Format, RowNum, Workbook};
+use std::io::Write;
+
+/// Linhas por planilha (limite do Excel, incluindo o cabeçalho).
+const MAX_ROWS: RowNum = 1_048_576;
+
+/// Largura mínima e máxima das colunas (em caracteres).
+const COLUMN_WIDTH: (usize, usize) = (10, 60);
+
+/// Gravação das linhas tipadas em XLSX (`--output-format xlsx`).
+///
+/// Textos (inclusive chaves de 44 dígitos) são células de texto, números
+/// são células numéricas e datas são células de data (DD/MM/AAAA).
+/// O cabeçalho fica em negrito e congelado. Ao atingir o limite de linhas
+/// do Excel, uma nova planilha é criada com o mesmo cabeçalho.
+pub struct XlsxOutput<W: Write> {
+    output: W,
+    workbook: Workbook,
+    header: Vec<String>,
+    header_format: Format,
+    date_format: Format,
+    /// Índice da planilha atual.
+    sheet: usize,
+    /// Próxima linha da planilha atual.
+    row: RowNum,
+}
+
+impl<W: Write> XlsxOutput<W> {
+    pub fn new(output: W, header: &[String]) -> UniqueResult<Self> {
+        let mut xlsx = Self {
+            output,
+            workbook: Workbook::new(),
+            header: header.to_vec(),
+            header_format: Format::new().set_bold(),
+            date_format: Format::new().set_num_format("dd/mm/yyyy"),
+            sheet: 0,
+            row: 0,
+        };
+        xlsx.add_sheet()?;
+        Ok(xlsx)
+    }
+
+    /// Cria uma planilha e grava o cabeçalho.
+    fn add_sheet(&mut self) -> UniqueResult<()> {
+        self.workbook.add_worksheet_with_constant_memory();
+        self.sheet = self.workbook.worksheets().len() - 1;
+
+        let worksheet = self.workbook.worksheet_from_index(self.sheet)?;
+        for (col, name) in self.header.iter().enumerate() {
+            let col = col as ColNum;
+            let width = name.chars().count().clamp(COLUMN_WIDTH.0, COLUMN_WIDTH.1);
+            worksheet.set_column_width(col, width as f64)?;
+            worksheet.write_string_with_format(0, col, name, &self.header_format)?;
+        }
+        worksheet.set_freeze_panes(1, 0)?;
+
+        self.row = 1;
+        Ok(())
+    }
+
+    /// Grava uma linha (valores na ordem do cabeçalho). Nulos ficam em branco.
+    ///
+    /// Linhas com erro não chegam aqui: com `--on-error keep-raw`, elas são descartadas.
+    pub fn push(&mut self, values: &[TypedValue]) -> UniqueResult<()> {
+        if self.row == MAX_ROWS {
+            self.add_sheet()?;
+        }
+
+        let row = self.row;
+        let worksheet = self.workbook.worksheet_from_index(self.sheet)?;
+        for (col, value) in values.iter().enumerate() {
+            let col = col as ColNum;
+            match value {
+                TypedValue::Null => continue,
+                TypedValue::Text(text) => worksheet.write_string(row, col, text)?,
+                TypedValue::Integer(n) => worksheet.write_number(row, col, *n as f64)?,
+                TypedValue::Float(n) => worksheet.write_number(row, col, *n)?,
+                // Números do Excel são f64: somas com mais de 15 dígitos são arredondadas
+                TypedValue::Decimal(n) => {
+                    worksheet.write_number(row, col, n.to_f64().unwrap_or_default())?
+                }
+                TypedValue::Date(date) => {
+                    worksheet.write_datetime_with_format(row, col, date, &self.date_format)?
+                }
+            };
+        }
+
+        self.row += 1;
+        Ok(())
+    }
+
+    /// Grava a pasta de trabalho na saída.
+    pub fn finish(mut self) -> UniqueResult<()> {
+        let bytes = self.workbook.save_to_buffer()?;
+        self.output.write_all(&bytes)?;
+        self.output.flush()?;
+        Ok(())
+    }
+}
+
+/// cargo test -- --show-output tests_xlsx_output
+#[cfg(test)]
+mod tests_xlsx_output {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_write_typed_rows() {
+        let header = vec![
+            "Chave".to_string(),
+            "Valor".to_string(),
+            "Emissão".to_string(),
+        ];
+
+        let mut bytes: Vec<u8> = Vec::new();
+        let mut xlsx = XlsxOutput::new(&mut bytes, &header).unwrap();
+        xlsx.push(&[
+            TypedValue::Text("35230100000000000000000000000000000000000001".to_string()),
+            TypedValue::Float(1250.5),
+            TypedValue::Date(NaiveDate::from_ymd_opt(2023, 5, 1).unwrap()),
+        ])
+        .unwrap();
+        xlsx.push(&[TypedValue::Null, TypedValue::Integer(3), TypedValue::Null])
+            .unwrap();
+        assert_eq!(xlsx.row, 3);
+        xlsx.finish().unwrap();
+
+        // Arquivo XLSX: um pacote zip
+        assert!(bytes.starts_with(b"PK"));
+    }
+}